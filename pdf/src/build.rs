@@ -25,7 +25,7 @@ impl PageBuilder {
             crop_box: Some(page.crop_box()?),
             trim_box: page.trim_box,
             resources: Some(page.resources()?.clone()),
-            rotate: page.rotate,
+            rotate: page.rotation()?,
         })
     }
     pub fn size(&mut self, width: f32, height: f32) {
@@ -64,7 +64,8 @@ impl CatalogBuilder {
             kids,
             resources: None,
             media_box: None,
-            crop_box: None
+            crop_box: None,
+            rotate: None,
         }, update)?;
 
         for (page, promise) in self.pages.into_iter().zip(kids_promise) {
@@ -75,7 +76,8 @@ impl CatalogBuilder {
                 crop_box: page.crop_box,
                 trim_box: page.trim_box,
                 resources: page.resources,
-                rotate: page.rotate,
+                rotate: Some(page.rotate),
+                annots: None,
             };
             update.fulfill(promise, PagesNode::Leaf(page))?;
         }
@@ -156,10 +156,17 @@ fn inline_image(lexer: &mut Lexer, resolve: &impl Resolve) -> Result<Arc<ImageXO
     let data_start = lexer.get_pos() + 1;
 
     // find the end before try parsing.
-    if lexer.seek_substr("\nEI").is_none() {
-        bail!("inline image exceeds expected data range");
-    }    
-    let data_end = lexer.get_pos() - 3;
+    // the data is terminated by whitespace, `EI` and another whitespace (or the end of the stream).
+    let is_whitespace = |b: u8| matches!(b, 0 | b' ' | b'\r' | b'\n' | b'\t');
+    let rest = lexer.get_remaining_slice();
+    let end = rest.windows(3).enumerate().skip(1).find(|&(i, w)| {
+        is_whitespace(w[0]) && &w[1..] == b"EI" && rest.get(i + 3).is_none_or(|&b| is_whitespace(b))
+    });
+    let data_end = match end {
+        Some((n, _)) => lexer.get_pos() + n,
+        None => bail!("inline image exceeds expected data range")
+    };
+    lexer.set_pos(data_end + 3);
 
     // ugh
     let bits_per_component = dict.get("BitsPerComponent").map(|p| p.as_integer()).transpose()?;
@@ -212,6 +219,7 @@ fn inline_image(lexer: &mut Lexer, resolve: &impl Resolve) -> Result<Arc<ImageXO
         struct_parent: None,
         id: None,
         smask: None,
+        matte: None,
         other: dict,
     };
 
@@ -221,18 +229,72 @@ fn inline_image(lexer: &mut Lexer, resolve: &impl Resolve) -> Result<Arc<ImageXO
 }
 
 struct OpBuilder {
-    last: Point,
-    compability_section: bool,
+    /// current point, `None` outside of a path
+    last: Option<Point>,
+    /// start of the current subpath, where `h` returns to
+    subpath_start: Point,
+    /// nesting depth of `BX`/`EX` compatibility sections
+    compability_depth: usize,
+    /// whether we are between `BT` and `ET`
+    in_text_object: bool,
+    /// whether a misplaced text op has been reported for this stream
+    text_object_warned: bool,
     ops: Vec<Op>
 }
 impl OpBuilder {
     fn new() -> Self {
         OpBuilder {
-            last: Point { x: 0., y: 0. },
-            compability_section: false,
+            last: None,
+            subpath_start: Point { x: 0., y: 0. },
+            compability_depth: 0,
+            in_text_object: false,
+            text_object_warned: false,
             ops: Vec::new()
         }
     }
+    /// Update the current point for the path ops pushed since `start`.
+    fn track_current_point(&mut self, start: usize) {
+        for op in &self.ops[start..] {
+            match *op {
+                Op::MoveTo { p } => {
+                    self.last = Some(p);
+                    self.subpath_start = p;
+                }
+                Op::Rect { rect } => {
+                    let p = Point { x: rect.x, y: rect.y };
+                    self.last = Some(p);
+                    self.subpath_start = p;
+                }
+                Op::LineTo { p } | Op::CurveTo { p, .. } => self.last = Some(p),
+                Op::Close => self.last = Some(self.subpath_start),
+                Op::EndPath | Op::Stroke | Op::Fill { .. } | Op::FillAndStroke { .. } => self.last = None,
+                _ => {}
+            }
+        }
+    }
+    /// Track `BT`/`ET` for the ops pushed since `start` and warn once per stream
+    /// about text objects that are nested or not closed properly. The ops are kept as they are.
+    fn check_text_object(&mut self, start: usize) {
+        for op in &self.ops[start..] {
+            let problem = match *op {
+                Op::BeginText if self.in_text_object => Some("BT inside a text object"),
+                Op::EndText if !self.in_text_object => Some("ET outside of a text object"),
+                Op::TextDraw { .. } | Op::TextDrawAdjusted { .. } if !self.in_text_object => Some("text shown outside of a text object"),
+                _ => None
+            };
+            match *op {
+                Op::BeginText => self.in_text_object = true,
+                Op::EndText => self.in_text_object = false,
+                _ => {}
+            }
+            if let Some(problem) = problem {
+                if !self.text_object_warned {
+                    warn!("{}", problem);
+                    self.text_object_warned = true;
+                }
+            }
+        }
+    }
     fn parse(&mut self, data: &[u8], resolve: &impl Resolve) -> Result<()> {
         let mut lexer = Lexer::new(data);
         let mut buffer = Vec::with_capacity(5);
@@ -253,11 +315,19 @@ impl OpBuilder {
                     lexer.set_pos(backup_pos);
                     let op = t!(lexer.next());
                     let operator = t!(op.as_str(), op);
+                    let start = self.ops.len();
                     match self.add(operator, buffer.drain(..), &mut lexer, resolve) {
-                        Ok(()) => {},
+                        Ok(()) => {
+                            self.track_current_point(start);
+                            self.check_text_object(start);
+                        }
                         Err(e) if resolve.options().allow_invalid_ops => {
                             warn!("OP Err: {:?}", e);
                         },
+                        // operators in a compatibility section may be ignored
+                        Err(e) if self.compability_depth > 0 => {
+                            warn!("OP Err in BX/EX section: {:?}", e);
+                        },
                         Err(e) => return Err(e),
                     }
                 }
@@ -297,11 +367,10 @@ impl OpBuilder {
                 properties: None
             }),
             "BT"  => push(Op::BeginText),
-            "BX"  => self.compability_section = true,
+            "BX"  => self.compability_depth += 1,
             "c"   => {
                 points!(args, c1, c2, p);
                 push(Op::CurveTo { c1, c2, p });
-                self.last = p;
             }
             "cm"  => {
                 numbers!(args, a, b, c, d, e, f);
@@ -321,8 +390,14 @@ impl OpBuilder {
                 let phase = args.next().ok_or(PdfError::NoOpArg)?.as_number()?;
                 push(Op::Dash { pattern, phase });
             }
-            "d0"  => {}
-            "d1"  => {}
+            "d0"  => {
+                numbers!(args, wx, wy);
+                push(Op::CharWidth { wx, wy, bbox: None });
+            }
+            "d1"  => {
+                numbers!(args, wx, wy, left, bottom, right, top);
+                push(Op::CharWidth { wx, wy, bbox: Some(crate::object::Rect { left, bottom, right, top }) });
+            }
             "Do" | "Do0" => {
                 names!(args, name);
                 push(Op::XObject { name });
@@ -334,7 +409,7 @@ impl OpBuilder {
             "EI"  => bail!("Parse Error. Unexpected 'EI'"),
             "EMC" => push(Op::EndMarkedContent),
             "ET"  => push(Op::EndText),
-            "EX"  => self.compability_section = false,
+            "EX"  => self.compability_depth = self.compability_depth.saturating_sub(1),
             "f" |
             "F"   => push(Op::Fill { winding: NonZero }),
             "f*"  => push(Op::Fill { winding: EvenOdd }),
@@ -350,7 +425,10 @@ impl OpBuilder {
                     0 => LineJoin::Miter,
                     1 => LineJoin::Round,
                     2 => LineJoin::Bevel,
-                    _ => bail!("invalid line join {}", n)
+                    _ => {
+                        warn!("invalid line join {}, using miter join", n);
+                        LineJoin::Miter
+                    }
                 };
                 push(Op::LineJoin { join });
             }
@@ -360,7 +438,10 @@ impl OpBuilder {
                     0 => LineCap::Butt,
                     1 => LineCap::Round,
                     2 => LineCap::Square,
-                    _ => bail!("invalid line cap {}", n)
+                    _ => {
+                        warn!("invalid line cap {}, using butt cap", n);
+                        LineCap::Butt
+                    }
                 };
                 push(Op::LineCap { cap });
             }
@@ -375,12 +456,10 @@ impl OpBuilder {
             "l"   => {
                 let p = point(&mut args)?;
                 push(Op::LineTo { p });
-                self.last = p;
             }
             "m"   => {
                 let p = point(&mut args)?;
                 push(Op::MoveTo { p });
-                self.last = p;
             }
             "M"   => push(Op::MiterLimit { limit: number(&mut args)? }),
             "MP"  => push(Op::MarkedContentPoint { tag: name(&mut args)?, properties: None }),
@@ -407,9 +486,7 @@ impl OpBuilder {
             "sc" | "scn" => {
                 push(Op::FillColor { color: Color::Other(args.collect()) });
             }
-            "sh"  => {
-
-            }
+            "sh"  => push(Op::Shade { name: name(&mut args)? }),
             "T*"  => push(Op::TextNewline),
             "Tc"  => push(Op::CharSpacing { char_space: number(&mut args)? }),
             "Td"  => push(Op::MoveTextPosition { translation: point(&mut args)? }),
@@ -460,8 +537,13 @@ impl OpBuilder {
             "Tz"  => push(Op::TextScaling { horiz_scale: number(&mut args)? }),
             "v"   => {
                 points!(args, c2, p);
-                push(Op::CurveTo { c1: self.last, c2, p });
-                self.last = p;
+                match self.last {
+                    Some(c1) => push(Op::CurveTo { c1, c2, p }),
+                    None => {
+                        warn!("v without current point");
+                        push(Op::MoveTo { p });
+                    }
+                }
             }
             "w"   => push(Op::LineWidth { width: number(&mut args)? }),
             "W"   => push(Op::Clip { winding: NonZero }),
@@ -469,7 +551,6 @@ impl OpBuilder {
             "y"   => {
                 points!(args, c1, p);
                 push(Op::CurveTo { c1, c2: p, p });
-                self.last = p;
             }
             "'"   => {
                 push(Op::TextNewline);
@@ -481,7 +562,7 @@ impl OpBuilder {
                 push(Op::TextNewline);
                 push(Op::TextDraw { text: string(&mut args)? });
             }
-            o if !self.compability_section => {
+            o if self.compability_depth == 0 => {
                 bail!("invalid operator {}", o)
             },
             _ => {}
@@ -622,7 +703,7 @@ pub fn serialize_ops(mut ops: &[Op]) -> Result<Vec<u8>> {
             Restore => writeln!(f, "Q")?,
             Transform { matrix } => writeln!(f, "{} cm", matrix)?,
             LineWidth { width } => writeln!(f, "{} w", width)?,
-            Dash { ref pattern, phase } => write!(f, "[{}] {} d", pattern.iter().format(" "), phase)?,
+            Dash { ref pattern, phase } => writeln!(f, "[{}] {} d", pattern.iter().format(" "), phase)?,
             LineJoin { join } => writeln!(f, "{} j", join as u8)?,
             LineCap { cap } => writeln!(f, "{} J", cap as u8)?,
             MiterLimit { limit } => writeln!(f, "{} M", limit)?,
@@ -714,6 +795,8 @@ pub fn serialize_ops(mut ops: &[Op]) -> Result<Vec<u8>> {
                 writeln!(f, "[{}] TJ", array.iter().format(" "))?;
             },
             InlineImage { image: _ } => unimplemented!(),
+            CharWidth { wx, wy, bbox: None } => writeln!(f, "{} {} d0", wx, wy)?,
+            CharWidth { wx, wy, bbox: Some(r) } => writeln!(f, "{} {} {} {} {} {} d1", wx, wy, r.left, r.bottom, r.right, r.top)?,
             XObject { ref name } => {
                 serialize_name(name, f)?;
                 writeln!(f, " Do")?;
@@ -912,6 +995,26 @@ pub enum Color {
     Cmyk(Cmyk),
     Other(Vec<Primitive>),
 }
+impl Color {
+    /// Split the operands of `sc`/`scn` into the color components and an optional trailing pattern name.
+    ///
+    /// For a colored pattern there are no components; for an uncolored one they give the paint color.
+    pub fn components_and_pattern(&self) -> Result<(Vec<f32>, Option<&str>)> {
+        match *self {
+            Color::Gray(g) => Ok((vec![g], None)),
+            Color::Rgb(Rgb { red, green, blue }) => Ok((vec![red, green, blue], None)),
+            Color::Cmyk(Cmyk { cyan, magenta, yellow, key }) => Ok((vec![cyan, magenta, yellow, key], None)),
+            Color::Other(ref args) => {
+                let (pattern, components) = match args.split_last() {
+                    Some((Primitive::Name(name), rest)) => (Some(name.as_str()), rest),
+                    _ => (None, &args[..]),
+                };
+                let components = components.iter().map(|p| p.as_number()).collect::<Result<_>>()?;
+                Ok((components, pattern))
+            }
+        }
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, DataSize)]
 pub enum TextMode {
@@ -1019,6 +1122,12 @@ pub enum Op {
     Transform { matrix: Matrix },
 
     LineWidth { width: f32 },
+
+    /// Set the dash pattern
+    /// 
+    /// An empty `pattern` means a solid line.
+    /// 
+    /// operator: `d`
     Dash { pattern: Vec<f32>, phase: f32 },
     LineJoin { join: LineJoin },
     LineCap { cap: LineCap },
@@ -1065,12 +1174,154 @@ pub enum Op {
     XObject { name: Name },
 
     InlineImage { image: Arc<ImageXObject> },
+
+    /// Width (and for `d1` the bounding box) of a Type3 glyph, in glyph space
+    /// 
+    /// Only valid as the first operator of a glyph description.
+    /// 
+    /// operators: `d0`, `d1`
+    CharWidth { wx: f32, wy: f32, bbox: Option<crate::object::Rect> },
+}
+
+/// Tracks the nesting of marked content sequences while walking through the ops of a content stream.
+#[derive(Debug, Default, Clone)]
+pub struct MarkedContent {
+    stack: Vec<(Name, Option<Primitive>)>,
+}
+impl MarkedContent {
+    pub fn new() -> Self {
+        MarkedContent::default()
+    }
+    /// Update the stack for `op`. An `EMC` without matching `BMC`/`BDC` is ignored.
+    pub fn update(&mut self, op: &Op) {
+        match *op {
+            Op::BeginMarkedContent { ref tag, ref properties } => self.stack.push((tag.clone(), properties.clone())),
+            Op::EndMarkedContent if self.stack.pop().is_none() => warn!("EMC without matching BMC/BDC"),
+            _ => {}
+        }
+    }
+    /// Tag of the innermost open sequence.
+    pub fn current(&self) -> Option<&Name> {
+        self.stack.last().map(|(tag, _)| tag)
+    }
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+    /// Open sequences, outermost first.
+    pub fn iter(&self) -> impl Iterator<Item=(&Name, Option<&Primitive>)> {
+        self.stack.iter().map(|(tag, props)| (tag, props.as_ref()))
+    }
+    /// Whether the current content is (part of) an `/Artifact`, like page headers or decorations.
+    pub fn in_artifact(&self) -> bool {
+        self.stack.iter().any(|(tag, _)| tag == "Artifact")
+    }
+    /// Properties of the enclosing `/OC` sequences, each either a name in the `/Properties` resources or an inline dictionary.
+    pub fn optional_content(&self) -> impl Iterator<Item=&Primitive> {
+        self.stack.iter().filter(|(tag, _)| tag == "OC").filter_map(|(_, props)| props.as_ref())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Rejects invalid operators instead of skipping them.
+    struct NoInvalidOps;
+    impl Resolve for NoInvalidOps {
+        fn resolve_flags(&self, r: PlainRef, flags: ParseFlags, depth: usize) -> Result<Primitive> {
+            NoResolve.resolve_flags(r, flags, depth)
+        }
+        fn get<T: Object+DataSize>(&self, r: Ref<T>) -> Result<RcRef<T>> {
+            NoResolve.get(r)
+        }
+        fn options(&self) -> &ParseOptions {
+            static OPTIONS: ParseOptions = {
+                let mut options = ParseOptions::strict();
+                options.allow_invalid_ops = false;
+                options
+            };
+            &OPTIONS
+        }
+        fn get_data_or_decode(&self, id: PlainRef, range: std::ops::Range<usize>, filters: &[StreamFilter]) -> Result<Arc<[u8]>> {
+            NoResolve.get_data_or_decode(id, range, filters)
+        }
+    }
+
+    #[test]
+    fn test_scn_pattern() {
+        let ops = parse_ops(b"0.2 0.4 0.6 /P1 scn /P2 SCN 0.5 sc", &NoInvalidOps).unwrap();
+        let colors: Vec<_> = ops.iter().map(|op| match *op {
+            Op::FillColor { ref color } | Op::StrokeColor { ref color } => color.components_and_pattern().unwrap(),
+            ref op => panic!("unexpected {:?}", op)
+        }).collect();
+        assert_eq!(colors[0], (vec![0.2, 0.4, 0.6], Some("P1")));
+        assert_eq!(colors[1], (vec![], Some("P2")));
+        assert_eq!(colors[2], (vec![0.5], None));
+    }
+
+    #[test]
+    fn test_v_current_point() {
+        let ops = parse_ops(b"1 2 m 3 4 l h 5 6 7 8 v n 5 6 7 8 v", &NoInvalidOps).unwrap();
+        match ops[3] {
+            Op::CurveTo { c1, .. } => assert_eq!(c1, Point { x: 1., y: 2. }),
+            ref op => panic!("unexpected {:?}", op)
+        }
+        assert!(matches!(ops[5], Op::MoveTo { p: Point { x, y } } if (x, y) == (7., 8.)));
+
+        let ops = parse_ops(b"1 2 3 4 re 5 6 7 8 v", &NoInvalidOps).unwrap();
+        assert!(matches!(ops[1], Op::CurveTo { c1: Point { x, y }, .. } if (x, y) == (1., 2.)));
+    }
+
+    #[test]
+    fn test_unbalanced_text_objects() {
+        let ops = parse_ops(b"BT BT (a) Tj ET ET (b) Tj", &NoInvalidOps).unwrap();
+        assert_eq!(ops.len(), 6);
+        assert!(matches!(ops[1], Op::BeginText));
+        assert!(matches!(ops[5], Op::TextDraw { .. }));
+    }
+
+    #[test]
+    fn test_text_object_state() {
+        // the invalid `Tj` is dropped and must not count as text shown outside BT/ET
+        let mut ops = OpBuilder::new();
+        ops.parse(b"Tj BT ET", &NoResolve).unwrap();
+        assert!(!ops.in_text_object);
+        assert!(!ops.text_object_warned);
+
+        let mut ops = OpBuilder::new();
+        ops.parse(b"ET BT BT (a) Tj ET (b) Tj", &NoResolve).unwrap();
+        assert!(!ops.in_text_object);
+        assert!(ops.text_object_warned);
+    }
+
+    #[test]
+    fn test_compatibility_section() {
+        let ops = parse_ops(b"BX BX foo EX /Foo j EX 0 0 m", &NoInvalidOps).unwrap();
+        assert!(matches!(ops[..], [Op::MoveTo { .. }]));
+        assert!(parse_ops(b"BX EX /Foo j", &NoInvalidOps).is_err());
+        assert!(parse_ops(b"BX EX foo", &NoInvalidOps).is_err());
+    }
+
+    #[test]
+    fn test_marked_content() {
+        let ops = parse_ops(b"/OC /MC0 BDC /Artifact BMC 0 0 m EMC /Span << /ActualText (x) >> BDC EMC EMC EMC", &NoResolve).unwrap();
+        let mut mc = MarkedContent::new();
+        let mut states = vec![];
+        for op in &ops {
+            mc.update(op);
+            states.push((mc.depth(), mc.current().map(|n| n.as_str().to_owned()), mc.in_artifact()));
+        }
+        assert_eq!(states[0], (1, Some("OC".into()), false));
+        assert_eq!(states[1], (2, Some("Artifact".into()), true));
+        assert_eq!(states[3], (1, Some("OC".into()), false));
+        assert_eq!(states[4], (2, Some("Span".into()), false));
+        assert_eq!(states.last().unwrap().0, 0);
+
+        mc.update(&ops[0]);
+        let oc: Vec<_> = mc.optional_content().collect();
+        assert!(matches!(oc[..], [Primitive::Name(ref n)] if n == "MC0"));
+    }
+
     #[test]
     fn test_inline_image() {
         let data = br###"
@@ -1087,4 +1338,87 @@ EI
         let mut lexer = Lexer::new(data);
         assert!(inline_image(&mut lexer, &NoResolve).is_ok()); 
     }
+
+    #[test]
+    fn test_inline_image_ops() {
+        let data = b"q BI /W 2 /H 2 /CS /G /BPC 8 ID aEI\xff EI Q";
+        let ops = parse_ops(data, &NoResolve).unwrap();
+        assert_eq!(ops.len(), 3);
+        match ops[1] {
+            Op::InlineImage { ref image } => {
+                assert_eq!(image.width, 2);
+                assert_eq!(image.height, 2);
+                assert_eq!(image.bits_per_component, Some(8));
+                assert!(matches!(image.color_space, Some(ColorSpace::DeviceGray)));
+                let data = image.inner.data(&NoResolve).unwrap();
+                assert_eq!(&*data, b"aEI\xff");
+            }
+            ref op => panic!("unexpected op {:?}", op)
+        }
+        assert!(matches!(ops[2], Op::Restore));
+    }
+
+    #[test]
+    fn test_char_width() {
+        let ops = parse_ops(b"500 0 d0 600 0 10 -5 590 700 d1", &NoResolve).unwrap();
+        match ops[..] {
+            [Op::CharWidth { wx: w0, bbox: None, .. }, Op::CharWidth { wx: w1, bbox: Some(r), .. }] => {
+                assert_eq!((w0, w1), (500., 600.));
+                assert_eq!((r.left, r.bottom, r.right, r.top), (10., -5., 590., 700.));
+            }
+            ref ops => panic!("unexpected ops {:?}", ops)
+        }
+    }
+
+    #[test]
+    fn test_shade() {
+        let ops = parse_ops(b"/Sh1 sh", &NoResolve).unwrap();
+        match ops[..] {
+            [Op::Shade { ref name }] => assert_eq!(name.as_str(), "Sh1"),
+            ref ops => panic!("unexpected ops {:?}", ops)
+        }
+    }
+
+    #[test]
+    fn test_line_cap() {
+        let ops = parse_ops(b"0 J 1 J 2 J 7 J", &NoResolve).unwrap();
+        let caps: Vec<_> = ops.iter().map(|op| match *op {
+            Op::LineCap { cap } => cap,
+            _ => panic!("unexpected op {:?}", op)
+        }).collect();
+        assert_eq!(caps, [LineCap::Butt, LineCap::Round, LineCap::Square, LineCap::Butt]);
+    }
+
+    #[test]
+    fn test_line_join() {
+        let ops = parse_ops(b"0 j 1 j 2 j -1 j", &NoResolve).unwrap();
+        let joins: Vec<_> = ops.iter().map(|op| match *op {
+            Op::LineJoin { join } => join,
+            _ => panic!("unexpected op {:?}", op)
+        }).collect();
+        assert_eq!(joins, [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel, LineJoin::Miter]);
+    }
+
+    #[test]
+    fn test_dash() {
+        let ops = vec![
+            Op::Dash { pattern: vec![3., 2.], phase: 1. },
+            Op::Dash { pattern: vec![], phase: 0. },
+            Op::Stroke,
+        ];
+        let data = serialize_ops(&ops).unwrap();
+        let ops = parse_ops(&data, &NoResolve).unwrap();
+        assert_eq!(ops.len(), 3);
+        match ops[0] {
+            Op::Dash { ref pattern, phase } => {
+                assert_eq!(pattern, &[3., 2.]);
+                assert_eq!(phase, 1.);
+            }
+            ref op => panic!("unexpected op {:?}", op)
+        }
+        match ops[1] {
+            Op::Dash { ref pattern, .. } => assert!(pattern.is_empty()),
+            ref op => panic!("unexpected op {:?}", op)
+        }
+    }
 }
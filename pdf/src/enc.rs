@@ -251,69 +251,169 @@ fn encode_85(data: &[u8]) -> Vec<u8> {
 }
 
 pub fn flate_decode(data: &[u8], params: &LZWFlateParams) -> Result<Vec<u8>> {
-    let predictor = params.predictor as usize;
-    let n_components = params.n_components as usize;
-    let columns = params.columns as usize;
-    let stride = columns * n_components;
-
     // First flate decode
     let decoded = match inflate_bytes_zlib(data) {
         Ok(data) => data,
         Err(_) => {
-            std::fs::write("/tmp/data", data).unwrap();
             info!("invalid zlib header. trying without");
             inflate_bytes(data)?
         }
     };
-    // Then unfilter (PNG)
-    // For this, take the old out as input, and write output to out
-
-    if predictor > 10 {
-        let inp = decoded; // input buffer
-        let rows = inp.len() / (stride+1);
-        
-        // output buffer
-        let mut out = vec![0; rows * stride];
-    
-        // Apply inverse predictor
-        let null_vec = vec![0; stride];
-        
-        let mut in_off = 0; // offset into input buffer
-        
-        let mut out_off = 0; // offset into output buffer
-        let mut last_out_off = 0; // last offset to output buffer
-        
-        while in_off + stride < inp.len() {
-            let predictor = PredictorType::from_u8(inp[in_off])?;
-            in_off += 1; // +1 because the first byte on each row is predictor
-            
-            let row_in = &inp[in_off .. in_off + stride];
-            let (prev_row, row_out) = if out_off == 0 {
-                (&null_vec[..], &mut out[out_off .. out_off+stride])
-            } else {
-                let (prev, curr) = out.split_at_mut(out_off);
-                (&prev[last_out_off ..], &mut curr[.. stride])
-            };
-            unfilter(predictor, n_components, prev_row, row_in, row_out);
-            
-            last_out_off = out_off;
-            
-            in_off += stride;
-            out_off += stride;
-        }
-        Ok(out)
-    } else {
-        Ok(decoded)
+    apply_predictor(decoded, params)
+}
+
+/// Undo the `/Predictor` of Flate or LZW compressed data: 2 for TIFF, 10 and above for PNG.
+pub fn apply_predictor(data: Vec<u8>, params: &LZWFlateParams) -> Result<Vec<u8>> {
+    let n_components = params.n_components.max(1) as usize;
+    let columns = params.columns.max(1) as usize;
+    let bits = || match params.bits_per_component {
+        n @ (1 | 2 | 4 | 8 | 16) => Ok(n as usize),
+        n => Err(other!("invalid BitsPerComponent {} for predictor", n))
+    };
+
+    match params.predictor {
+        2 => {
+            let bits = bits()?;
+            let stride = (columns * n_components * bits).div_ceil(8);
+            let mut out = data;
+            for row in out.chunks_exact_mut(stride) {
+                tiff_unpredict(row, columns, n_components, bits);
+            }
+            Ok(out)
+        }
+        p if p >= 10 => {
+            let bits = bits()?;
+            // bytes per row, and per pixel (at least one) for the PNG filters
+            let stride = (columns * n_components * bits).div_ceil(8);
+            let bpp = (n_components * bits).div_ceil(8);
+
+            let inp = data; // input buffer
+            let rows = inp.len() / (stride+1);
+
+            // output buffer
+            let mut out = vec![0; rows * stride];
+
+            // Apply inverse predictor
+            let null_vec = vec![0; stride];
+
+            let mut prev_off = None; // offset of the previous row in the output buffer
+            for (i, row) in inp.chunks_exact(stride + 1).enumerate() {
+                // the first byte on each row is the predictor
+                let predictor = PredictorType::from_u8(row[0])?;
+                let (prev, curr) = out.split_at_mut(i * stride);
+                let prev_row = match prev_off {
+                    Some(off) => &prev[off ..],
+                    None => &null_vec[..]
+                };
+                unfilter(predictor, bpp, prev_row, &row[1..], &mut curr[.. stride]);
+                prev_off = Some(i * stride);
+            }
+            Ok(out)
+        }
+        _ => Ok(data)
+    }
+}
+
+/// TIFF predictor 2: each sample is stored as the difference to the sample of the same component to its left.
+fn tiff_unpredict(row: &mut [u8], columns: usize, n_components: usize, bits: usize) {
+    match bits {
+        8 => {
+            for i in n_components .. columns * n_components {
+                row[i] = row[i].wrapping_add(row[i - n_components]);
+            }
+        }
+        16 => {
+            for i in n_components .. columns * n_components {
+                let left = u16::from_be_bytes([row[2 * (i - n_components)], row[2 * (i - n_components) + 1]]);
+                let v = u16::from_be_bytes([row[2 * i], row[2 * i + 1]]).wrapping_add(left);
+                row[2 * i .. 2 * i + 2].copy_from_slice(&v.to_be_bytes());
+            }
+        }
+        _ => {
+            let mask = (1u8 << bits) - 1;
+            let get = |row: &[u8], i: usize| (row[i * bits / 8] >> (8 - bits - i * bits % 8)) & mask;
+            for i in n_components .. columns * n_components {
+                let v = get(row, i).wrapping_add(get(row, i - n_components)) & mask;
+                let shift = 8 - bits - i * bits % 8;
+                let byte = &mut row[i * bits / 8];
+                *byte = (*byte & !(mask << shift)) | (v << shift);
+            }
+        }
     }
 }
 fn flate_encode(data: &[u8]) -> Vec<u8> {
     deflate_bytes(data)
 }
 
-pub fn dct_decode(data: &[u8], _params: &DCTDecodeParams) -> Result<Vec<u8>> {
-    use jpeg_decoder::Decoder;
+/// Whether the JPEG data contains an `APP14` segment written by Adobe software.
+fn has_adobe_marker(data: &[u8]) -> bool {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return false;
+    }
+    let mut pos = 2;
+    while let Some(&[0xFF, marker, len_hi, len_lo]) = data.get(pos .. pos + 4) {
+        match marker {
+            // start of scan: no more header segments
+            0xDA => break,
+            0xEE if data[pos + 4 ..].starts_with(b"Adobe") => return true,
+            _ => pos += 2 + u16::from_be_bytes([len_hi, len_lo]) as usize,
+        }
+    }
+    false
+}
+
+/// The sample precision from the start of frame header.
+fn jpeg_precision(data: &[u8]) -> Option<u8> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+    let mut pos = 2;
+    while let Some(&[0xFF, marker, len_hi, len_lo]) = data.get(pos .. pos + 4) {
+        match marker {
+            0xDA => break,
+            0xC0 ..= 0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => return data.get(pos + 4).copied(),
+            _ => pos += 2 + u16::from_be_bytes([len_hi, len_lo]) as usize,
+        }
+    }
+    None
+}
+
+/// Decode JPEG data into interleaved 8 bit samples.
+/// 
+/// Depending on the number of components the result is gray, RGB or CMYK.
+pub fn dct_decode(data: &[u8], params: &DCTDecodeParams) -> Result<Vec<u8>> {
+    use jpeg_decoder::{Decoder, PixelFormat, ColorTransform};
     let mut decoder = Decoder::new(data);
-    let pixels = decoder.decode()?;
+    decoder.read_info()?;
+    let pixel_format = try_opt!(decoder.info()).pixel_format;
+
+    // an explicit /ColorTransform takes precedence over the JPEG markers
+    match (params.color_transform, pixel_format) {
+        (Some(0), PixelFormat::RGB24) => decoder.set_color_transform(ColorTransform::RGB),
+        (Some(1), PixelFormat::RGB24) => decoder.set_color_transform(ColorTransform::YCbCr),
+        (Some(0), PixelFormat::CMYK32) => decoder.set_color_transform(ColorTransform::CMYK),
+        (Some(1), PixelFormat::CMYK32) => decoder.set_color_transform(ColorTransform::YCCK),
+        _ => {}
+    }
+    let mut pixels = decoder.decode()?;
+
+    match pixel_format {
+        // 16 bit samples are in native byte order and only use the low `precision` bits.
+        // keep the upper 8 of those.
+        PixelFormat::L16 => {
+            let shift = jpeg_precision(data).unwrap_or(16).clamp(8, 16) - 8;
+            pixels = pixels.chunks_exact(2).map(|c| (u16::from_ne_bytes([c[0], c[1]]) >> shift) as u8).collect();
+        }
+
+        // the decoder inverts CMYK data, as Adobe software stores it inverted.
+        // undo that for all other files.
+        PixelFormat::CMYK32 if !has_adobe_marker(data) && params.color_transform != Some(1) => {
+            for b in pixels.iter_mut() {
+                *b = 255 - *b;
+            }
+        }
+        _ => {}
+    }
     Ok(pixels)
 }
 
@@ -330,7 +430,7 @@ pub fn lzw_decode(data: &[u8], params: &LZWFlateParams) -> Result<Vec<u8>> {
     decoder
         .into_stream(&mut out)
         .decode_all(data).status?;
-    Ok(out)
+    apply_predictor(out, params)
 }
 fn lzw_encode(data: &[u8], params: &LZWFlateParams) -> Result<Vec<u8>> {
     use weezl::{BitOrder, encode::Encoder};
@@ -344,32 +444,224 @@ fn lzw_encode(data: &[u8], params: &LZWFlateParams) -> Result<Vec<u8>> {
     Ok(compressed)
 }
 
+/// Decode CCITT Group 3 (`K >= 0`) or Group 4 (`K < 0`) data into one byte per pixel.
+///
+/// Black pixels are 0 and white pixels 255, unless `BlackIs1` is set.
 pub fn fax_decode(data: &[u8], params: &CCITTFaxDecodeParams) -> Result<Vec<u8>> {
     use fax::{Color, decoder::{pels, decode_g4}};
 
-    if params.k < 0 {
-        let columns = params.columns as usize;
-        let rows = params.rows as usize;
+    let columns = params.columns as usize;
+    let rows = params.rows as usize;
+    if columns == 0 || columns > u16::MAX as usize {
+        bail!("invalid number of columns: {}", columns);
+    }
+    let (black, white) = if params.black_is_1 { (255, 0) } else { (0, 255) };
 
-        let height = if params.rows == 0 { None } else { Some(params.rows as u16)};
-        let mut buf = Vec::with_capacity(columns * rows);
-        decode_g4(data.iter().cloned(), columns as u16, height, |line| {
+    let mut buf = Vec::with_capacity(columns * rows);
+    let mut push_line = |line: &[u16]| {
+        if rows == 0 || buf.len() < columns * rows {
             buf.extend(pels(line, columns as u16).map(|c| match c {
-                Color::Black => 0,
-                Color::White => 255
+                Color::Black => black,
+                Color::White => white,
             }));
-            assert_eq!(buf.len() % columns, 0, "len={}, columns={}", buf.len(), columns);
-        }).ok_or(PdfError::Other { msg: "faxdecode failed".into() })?;
-        assert_eq!(buf.len() % columns, 0, "len={}, columns={}", buf.len(), columns);
-
-        if rows != 0 && buf.len() != columns * rows {
-            bail!("decoded length does not match (expected {rows}∙{columns}, got {})", buf.len());
         }
-        Ok(buf)
+    };
+    if params.k < 0 {
+        let height = if params.rows == 0 { None } else { Some(params.rows as u16)};
+        let status = decode_g4(data.iter().cloned(), columns as u16, height, push_line);
+        // accept truncated data as long as all rows were decoded
+        if status.is_none() && (rows == 0 || buf.len() < columns * rows) {
+            bail!("faxdecode failed");
+        }
     } else {
-        unimplemented!()
+        g3_decode(data, params, &mut push_line)?;
+    }
+
+    if rows != 0 && buf.len() != columns * rows {
+        bail!("decoded length does not match (expected {rows}∙{columns}, got {})", buf.len());
+    }
+    Ok(buf)
+}
+
+/// Bit reader for fax data that reads zeros past the end of the input.
+struct FaxBits<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+impl FaxBits<'_> {
+    fn remaining(&self) -> usize {
+        (self.data.len() * 8).saturating_sub(self.pos)
+    }
+    fn align(&mut self) {
+        self.pos = (self.pos + 7) & !7;
     }
 }
+impl fax::BitReader for FaxBits<'_> {
+    type Error = std::convert::Infallible;
+
+    fn peek(&self, bits: u8) -> Option<u16> {
+        if bits > 16 {
+            return None;
+        }
+        let val = (self.pos .. self.pos + bits as usize).fold(0, |val, p| {
+            let bit = self.data.get(p / 8).map_or(0, |&b| (b >> (7 - p % 8)) & 1);
+            val << 1 | bit as u16
+        });
+        Some(val)
+    }
+    fn consume(&mut self, bits: u8) -> std::result::Result<(), Self::Error> {
+        self.pos += bits as usize;
+        Ok(())
+    }
+    fn bits_to_byte_boundary(&self) -> u8 {
+        ((8 - self.pos % 8) % 8) as u8
+    }
+}
+
+/// Read one run length including its makeup codes.
+fn fax_run(reader: &mut FaxBits, color: fax::Color) -> Option<usize> {
+    use fax::{Color, maps::{black, white}};
+
+    let mut total = 0;
+    loop {
+        let n = match color {
+            Color::White => white::decode(reader)?,
+            Color::Black => black::decode(reader)?,
+        } as usize;
+        total += n;
+        if n < 64 {
+            return Some(total);
+        }
+    }
+}
+
+/// Decode a one-dimensional (Modified Huffman) coded line into `line`.
+fn fax_line_1d(reader: &mut FaxBits, columns: usize, line: &mut Vec<u16>) -> Option<()> {
+    use fax::Color;
+
+    let mut a0 = 0;
+    let mut color = Color::White;
+    while a0 < columns {
+        a0 += fax_run(reader, color)?;
+        if a0 < columns {
+            line.push(a0 as u16);
+        }
+        color = !color;
+    }
+    Some(())
+}
+
+/// Decode a two-dimensional (Modified READ) coded line into `line`,
+/// using the changing elements of the previous line in `reference`.
+fn fax_line_2d(reader: &mut FaxBits, columns: usize, reference: &[u16], line: &mut Vec<u16>) -> Option<()> {
+    use fax::{Color, maps::{mode, Mode}};
+
+    // a0 starts on an imaginary white pixel in front of the line
+    let mut a0: isize = -1;
+    let mut color = Color::White;
+    while a0 < columns as isize {
+        // b1 is the first changing element right of a0 that switches to the opposite color,
+        // i.e. one at an even index when a0 is white
+        let parity = if color == Color::White { 0 } else { 1 };
+        let i = (0 .. reference.len())
+            .find(|&i| i % 2 == parity && reference[i] as isize > a0)
+            .unwrap_or(reference.len());
+        let b1 = reference.get(i).map_or(columns, |&b| b as usize);
+        let b2 = reference.get(i + 1).map_or(columns, |&b| b as usize);
+
+        match mode::decode(reader)? {
+            Mode::Pass => a0 = b2 as isize,
+            Mode::Horizontal => {
+                let a1 = a0.max(0) as usize + fax_run(reader, color)?;
+                let a2 = a1 + fax_run(reader, !color)?;
+                for a in [a1, a2] {
+                    if a < columns {
+                        line.push(a as u16);
+                    }
+                }
+                a0 = a2 as isize;
+            }
+            Mode::Vertical(delta) => {
+                let a1 = b1 as isize + delta as isize;
+                if a1 < a0.max(0) || a1 > columns as isize {
+                    return None;
+                }
+                if a1 < columns as isize {
+                    line.push(a1 as u16);
+                }
+                a0 = a1;
+                color = !color;
+            }
+            Mode::Extension | Mode::EOF => return None,
+        }
+    }
+    Some(())
+}
+
+/// Decode Group 3 data, which may mix one- and two-dimensional lines when `K > 0`.
+///
+/// EOL codes in front of a line are optional unless `EndOfLine` is set, and
+/// decoding stops at the end of the data, after `Rows` lines or at an RTC.
+fn g3_decode(data: &[u8], params: &CCITTFaxDecodeParams, push_line: &mut impl FnMut(&[u16])) -> Result<()> {
+    use fax::BitReader;
+
+    let columns = params.columns as usize;
+    let rows = params.rows as usize;
+    let mut reader = FaxBits { data, pos: 0 };
+    let mut reference = vec![];
+    let mut line = vec![];
+    let mut row = 0;
+    let mut missing_eol = false;
+    while rows == 0 || row < rows {
+        if params.encoded_byte_align {
+            reader.align();
+        }
+        // skip fill bits and the EOL code in front of the line
+        let mut eol = false;
+        loop {
+            if reader.remaining() == 0 {
+                return Ok(());
+            }
+            match reader.peek(12) {
+                Some(0) => reader.consume(1).ok(),
+                Some(1) => {
+                    eol = true;
+                    reader.consume(12).ok();
+                    break;
+                }
+                _ => break,
+            };
+        }
+        if params.end_of_line && !eol && !missing_eol {
+            warn!("CCITT line {} is not preceded by an EOL", row);
+            missing_eol = true;
+        }
+        // a second EOL means this is the RTC at the end of the data
+        let rtc = if params.k > 0 { reader.peek(13) == Some(0x1001) } else { reader.peek(12) == Some(1) };
+        if eol && rtc {
+            return Ok(());
+        }
+
+        let two_dimensional = params.k > 0 && {
+            let tag = reader.peek(1);
+            reader.consume(1).ok();
+            tag == Some(0)
+        };
+        line.clear();
+        let decoded = if two_dimensional {
+            fax_line_2d(&mut reader, columns, &reference, &mut line)
+        } else {
+            fax_line_1d(&mut reader, columns, &mut line)
+        };
+        if decoded.is_none() {
+            bail!("invalid CCITT data in line {}", row);
+        }
+        push_line(&line);
+        std::mem::swap(&mut reference, &mut line);
+        row += 1;
+    }
+    Ok(())
+}
 
 pub fn run_length_decode(data: &[u8]) -> Result<Vec<u8>> {
     // Used <http://benno.id.au/refs/PDFReference15_v5.pdf> as specification
@@ -410,10 +702,11 @@ pub fn set_jbig2_decoder(f: Box<DecodeFn>) {
 }
 
 pub fn jpx_decode(data: &[u8]) -> Result<Vec<u8>> {
-    JPX_DECODER.get().ok_or_else(|| PdfError::Other { msg: "jp2k decoder not set".into()})?(data)
+    JPX_DECODER.get().ok_or(PdfError::MissingDecoder { filter: "JPXDecode", setter: "set_jpx_decoder" })?(data)
 }
+/// The decoder is expected to return packed 1-bit samples, like an uncompressed image mask.
 pub fn jbig2_decode(data: &[u8]) -> Result<Vec<u8>> {
-    JBIG2_DECODER.get().ok_or_else(|| PdfError::Other { msg: "jbig2 decoder not set".into()})?(data)
+    JBIG2_DECODER.get().ok_or(PdfError::MissingDecoder { filter: "JBIG2Decode", setter: "set_jbig2_decoder" })?(data)
 }
 
 pub fn decode(data: &[u8], filter: &StreamFilter) -> Result<Vec<u8>> {
@@ -579,6 +872,35 @@ pub fn filter(method: PredictorType, bpp: usize, previous: &[u8], current: &mut
 mod tests {
     use super::*;
 
+    #[test]
+    fn adobe_marker() {
+        let app0 = [0xFF, 0xE0, 0x00, 0x07, b'J', b'F', b'I', b'F', 0x00];
+        let app14 = [0xFF, 0xEE, 0x00, 0x0E, b'A', b'd', b'o', b'b', b'e', 0, 100, 0, 0, 0, 0, 2];
+        let sos = [0xFF, 0xDA, 0x00, 0x02];
+
+        let jpeg = [&[0xFF, 0xD8][..], &app0, &app14, &sos].concat();
+        assert!(has_adobe_marker(&jpeg));
+
+        let jpeg = [&[0xFF, 0xD8][..], &app0, &sos, &app14].concat();
+        assert!(!has_adobe_marker(&jpeg));
+
+        assert!(!has_adobe_marker(&app14));
+    }
+
+    #[test]
+    fn jpeg_frame_precision() {
+        let app0 = [0xFF, 0xE0, 0x00, 0x07, b'J', b'F', b'I', b'F', 0x00];
+        let dht = [0xFF, 0xC4, 0x00, 0x03, 0x00];
+        let sof3 = [0xFF, 0xC3, 0x00, 0x0B, 12, 0, 1, 0, 1, 1, 1, 0x11, 0];
+        let sos = [0xFF, 0xDA, 0x00, 0x02];
+
+        let jpeg = [&[0xFF, 0xD8][..], &app0, &dht, &sof3, &sos].concat();
+        assert_eq!(jpeg_precision(&jpeg), Some(12));
+
+        let jpeg = [&[0xFF, 0xD8][..], &app0, &sos, &sof3].concat();
+        assert_eq!(jpeg_precision(&jpeg), None);
+    }
+
     #[test]
     fn base_85() {
         fn s(b: &[u8]) -> &str { std::str::from_utf8(b).unwrap() }
@@ -606,4 +928,128 @@ mod tests {
         let x = run_length_decode(&[254, b'a', 255, b'b', 2, b'c', b'b', b'c', 254, b'a', 128]).unwrap();
         assert_eq!(b"aaabbcbcaaa", x.as_slice());
     }
+
+    #[test]
+    fn predictor_test() {
+        let params = |predictor, n_components, bits_per_component, columns| LZWFlateParams {
+            predictor, n_components, bits_per_component, columns, early_change: 1
+        };
+        // two RGB pixels per row: Sub on the first row, Up on the second (as with /Predictor 15)
+        let data = [
+            1, 10, 20, 30, 1, 2, 3,
+            2, 1, 1, 1, 2, 2, 2,
+        ];
+        let out = flate_decode(&flate_encode(&data), &params(15, 3, 8, 2)).unwrap();
+        assert_eq!(out, [10, 20, 30, 11, 22, 33, 11, 21, 31, 13, 24, 35]);
+
+        // /Predictor 10 is PNG as well
+        let out = apply_predictor(vec![0, 5, 6], &params(10, 1, 8, 2)).unwrap();
+        assert_eq!(out, [5, 6]);
+
+        // TIFF with 8 and 4 bits per component
+        let out = apply_predictor(vec![10, 1, 1, 20, 2, 2], &params(2, 1, 8, 3)).unwrap();
+        assert_eq!(out, [10, 11, 12, 20, 22, 24]);
+        let out = apply_predictor(vec![0x31, 0x20], &params(2, 1, 4, 3)).unwrap();
+        assert_eq!(out, [0x34, 0x60]);
+
+        // no prediction: BitsPerComponent is not checked
+        let out = apply_predictor(vec![1, 2, 3], &params(1, 1, 3, 8)).unwrap();
+        assert_eq!(out, [1, 2, 3]);
+        assert!(apply_predictor(vec![1, 2, 3], &params(2, 1, 3, 8)).is_err());
+    }
+
+    #[test]
+    fn missing_decoder() {
+        assert!(matches!(jbig2_decode(&[]), Err(PdfError::MissingDecoder { filter: "JBIG2Decode", .. })));
+    }
+
+    #[test]
+    fn fax_decode_test() {
+        use fax::{Color, VecWriter, encoder::Encoder};
+
+        // 16x2 image: left half black on the first row, right half on the second
+        let rows = [[Color::Black, Color::White], [Color::White, Color::Black]];
+        let mut encoder = Encoder::new(VecWriter::new());
+        for row in rows.iter() {
+            let pels = row.iter().flat_map(|&c| std::iter::repeat_n(c, 8));
+            encoder.encode_line(pels, 16).unwrap();
+        }
+        let data = encoder.finish().unwrap().finish();
+
+        let mut params = CCITTFaxDecodeParams {
+            k: -1,
+            end_of_line: false,
+            encoded_byte_align: false,
+            columns: 16,
+            rows: 2,
+            end_of_block: true,
+            black_is_1: false,
+            damaged_rows_before_error: 0,
+        };
+        let decoded = fax_decode(&data, &params).unwrap();
+        assert_eq!(&decoded[..9], &[0, 0, 0, 0, 0, 0, 0, 0, 255]);
+        assert_eq!(&decoded[16..25], &[255, 255, 255, 255, 255, 255, 255, 255, 0]);
+
+        params.black_is_1 = true;
+        let inverted = fax_decode(&data, &params).unwrap();
+        assert!(decoded.iter().zip(&inverted).all(|(&a, &b)| a == 255 - b));
+    }
+
+    #[test]
+    fn fax_decode_g3_test() {
+        // pack a string of '0' and '1', ignoring spaces
+        fn bits(s: &str) -> Vec<u8> {
+            let bits: Vec<u8> = s.bytes().filter(|&b| b != b' ').map(|b| b - b'0').collect();
+            bits.chunks(8).map(|c| c.iter().enumerate().fold(0, |acc, (i, &b)| acc | b << (7 - i))).collect()
+        }
+        const EOL: &str = "000000000001";
+        // white 0, black 8, white 8
+        const ROW1: &str = "00110101 000101 10011";
+        // white 8, black 8
+        const ROW2: &str = "10011 000101";
+
+        let mut params = CCITTFaxDecodeParams {
+            k: 0,
+            end_of_line: false,
+            encoded_byte_align: false,
+            columns: 16,
+            rows: 2,
+            end_of_block: false,
+            black_is_1: false,
+            damaged_rows_before_error: 0,
+        };
+        let expected: Vec<u8> = [0, 255, 255, 0].iter().flat_map(|&c| std::iter::repeat_n(c, 8)).collect();
+
+        // two all-white rows without EOL codes
+        let white = fax_decode(&bits("101010 101010"), &params).unwrap();
+        assert_eq!(white, vec![255; 32]);
+
+        // one-dimensional without and with EOL codes
+        assert_eq!(fax_decode(&bits(&[ROW1, ROW2].join("")), &params).unwrap(), expected);
+        params.end_of_line = true;
+        assert_eq!(fax_decode(&bits(&[EOL, ROW1, EOL, ROW2].join("")), &params).unwrap(), expected);
+
+        // RTC ends the data when the number of rows is unknown
+        params.rows = 0;
+        params.end_of_block = true;
+        let rtc = EOL.repeat(6);
+        assert_eq!(fax_decode(&bits(&[EOL, ROW1, EOL, ROW2, &rtc].join("")), &params).unwrap(), expected);
+
+        // byte aligned lines
+        params.end_of_line = false;
+        params.encoded_byte_align = true;
+        params.rows = 2;
+        assert_eq!(fax_decode(&bits(&[ROW1, "00000", ROW2].join("")), &params).unwrap(), expected);
+
+        // mixed: a 1D line, a horizontal mode line and a line of two V0 codes
+        params.k = 2;
+        params.encoded_byte_align = false;
+        params.end_of_line = true;
+        params.rows = 0;
+        let rtc = format!("{EOL}1").repeat(6);
+        let data = [EOL, "1", ROW1, EOL, "0 001", ROW2, EOL, "0 1 1", &rtc].join("");
+        let decoded = fax_decode(&bits(&data), &params).unwrap();
+        assert_eq!(&decoded[..32], &expected[..]);
+        assert_eq!(&decoded[32..], &expected[16..]);
+    }
 }
@@ -1,15 +1,21 @@
 use std::collections::HashMap;
 use istring::SmallString;
 use crate as pdf;
-use crate::object::{Object, Resolve};
+use crate::object::{Object, Resolve, NoResolve, Stream};
 use crate::primitive::Primitive;
-use crate::error::{Result};
+use crate::parser::{Lexer, parse_with_lexer, ParseFlags};
+use crate::error::{Result, PdfError};
+use std::convert::TryFrom;
+use std::path::Path;
 use datasize::DataSize;
+use once_cell::sync::OnceCell;
 
 #[derive(Debug, Clone, DataSize)]
 pub struct Encoding {
     pub base: BaseEncoding,
     pub differences: HashMap<u32, SmallString>,
+    /// embedded or predefined CMap of a Type0 font
+    pub cmap: Option<CMap>,
 }
 
 #[derive(Object, Debug, Clone, Eq, PartialEq, DataSize)]
@@ -21,19 +27,45 @@ pub enum BaseEncoding {
     MacExpertEncoding,
     #[pdf(name="Identity-H")]
     IdentityH,
+    #[pdf(name="Identity-V")]
+    IdentityV,
     None,
 
     #[pdf(other)]
     Other(String),
 }
+impl BaseEncoding {
+    /// The glyph name for `code` in one of the simple encodings of Appendix D of the PDF spec.
+    ///
+    /// `MacExpertEncoding` is not supported.
+    pub fn glyph_name(&self, code: u8) -> Option<&'static str> {
+        let high: &[(u8, &str)] = match *self {
+            BaseEncoding::StandardEncoding => match code {
+                39 => return Some("quoteright"),
+                96 => return Some("quoteleft"),
+                _ => &STANDARD_GLYPHS
+            }
+            BaseEncoding::WinAnsiEncoding => &WIN_ANSI_GLYPHS,
+            BaseEncoding::MacRomanEncoding => &MAC_ROMAN_GLYPHS,
+            BaseEncoding::SymbolEncoding => return crate::font::metrics::builtin_glyph_name("Symbol", code),
+            _ => return None
+        };
+        match code {
+            32 ..= 126 => Some(ASCII_GLYPHS[code as usize - 32]),
+            _ => high.binary_search_by_key(&code, |&(c, _)| c).ok().map(|i| high[i].1)
+        }
+    }
+}
 impl Object for Encoding {
     fn from_primitive(p: Primitive, resolve: &impl Resolve) -> Result<Self> {
         match p {
             name @ Primitive::Name(_) => { 
-                Ok(Encoding {
-                    base: BaseEncoding::from_primitive(name, resolve)?,
-                    differences: HashMap::new(),
-                })
+                let base = BaseEncoding::from_primitive(name, resolve)?;
+                let cmap = match base {
+                    BaseEncoding::Other(ref name) => CMap::predefined(name, resolve.options().cmap_dir.as_deref()),
+                    _ => None
+                };
+                Ok(Encoding { base, differences: HashMap::new(), cmap })
             }
             Primitive::Dictionary(mut dict) => {
                 let base = match dict.remove("BaseEncoding") {
@@ -52,22 +84,615 @@ impl Object for Encoding {
                                 differences.insert(gid, name);
                                 gid += 1;
                             },
-                            _ => panic!()
+                            p => warn!("unexpected {} in /Differences", p.get_debug_name())
                         }
                     }
                 }
-                Ok(Encoding { base, differences })
+                Ok(Encoding { base, differences, cmap: None })
+            }
+            Primitive::Stream(stream) => {
+                let stream: Stream<()> = Stream::from_stream(stream, resolve)?;
+                let mut cmap = t!(CMap::parse(&t!(stream.data(resolve))));
+                let parent = cmap.use_cmap.as_deref()
+                    .and_then(|name| CMap::predefined(name, resolve.options().cmap_dir.as_deref()));
+                if let Some(parent) = parent {
+                    cmap.merge_parent(parent);
+                }
+                let base = match cmap.name {
+                    Some(ref name) => BaseEncoding::Other(name.clone()),
+                    None => BaseEncoding::None
+                };
+                Ok(Encoding { base, differences: HashMap::new(), cmap: Some(cmap) })
             }
             Primitive::Reference(r) => Self::from_primitive(resolve.resolve(r)?, resolve),
-            _ => panic!()
+            p => Err(PdfError::UnexpectedPrimitive { expected: "Name, Dictionary or Stream", found: p.get_debug_name() })
         }
     }
 }
 impl Encoding { 
+    /// The CMap of a Type0 font, either embedded or predefined.
+    ///
+    /// Predefined CMaps other than `Identity-H`/`V` are only found if `ParseOptions::cmap_dir` is set.
+    pub fn cmap(&self) -> Option<&CMap> {
+        static IDENTITY_H: OnceCell<CMap> = OnceCell::new();
+        static IDENTITY_V: OnceCell<CMap> = OnceCell::new();
+        match (&self.cmap, &self.base) {
+            (Some(cmap), _) => Some(cmap),
+            (None, BaseEncoding::IdentityH) => Some(IDENTITY_H.get_or_init(|| CMap::identity(false))),
+            (None, BaseEncoding::IdentityV) => Some(IDENTITY_V.get_or_init(|| CMap::identity(true))),
+            _ => None
+        }
+    }
+    /// The glyph name for `code`, from `/Differences` or the base encoding.
+    pub fn glyph_name(&self, code: u32) -> Option<&str> {
+        match self.differences.get(&code) {
+            Some(name) => Some(name.as_str()),
+            None => u8::try_from(code).ok().and_then(|code| self.base.glyph_name(code))
+        }
+    }
     pub fn standard() -> Encoding {
         Encoding {
             base: BaseEncoding::StandardEncoding,
-            differences: HashMap::new()
+            differences: HashMap::new(),
+            cmap: None,
+        }
+    }
+}
+
+/// A CMap, mapping the character codes of a show-text string to CIDs.
+#[derive(Debug, Clone, Default, DataSize)]
+pub struct CMap {
+    /// `/CMapName`
+    pub name: Option<String>,
+    /// `/WMode 1`: glyphs are laid out top to bottom
+    pub vertical: bool,
+    pub codespace: Vec<CodespaceRange>,
+    /// `(low, high, cid of low)`, from `cidrange` and `cidchar` sections
+    pub cid_ranges: Vec<(u32, u32, u32)>,
+    /// name of the CMap pulled in by `usecmap`
+    pub use_cmap: Option<String>,
+}
+
+/// Codes of `low.len()` bytes where every byte lies between the corresponding bytes of `low` and `high`.
+#[derive(Debug, Clone, DataSize)]
+pub struct CodespaceRange {
+    pub low: Vec<u8>,
+    pub high: Vec<u8>,
+}
+impl CodespaceRange {
+    fn matches(&self, code: &[u8]) -> bool {
+        code.len() == self.low.len() &&
+            code.iter().zip(&self.low).zip(&self.high).all(|((b, lo), hi)| lo <= b && b <= hi)
+    }
+}
+
+fn code_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, &b| acc << 8 | b as u32)
+}
+
+impl CMap {
+    /// The `Identity-H`/`Identity-V` CMap: two byte codes, CID = code.
+    pub fn identity(vertical: bool) -> CMap {
+        CMap {
+            name: Some(if vertical { "Identity-V" } else { "Identity-H" }.into()),
+            vertical,
+            codespace: vec![CodespaceRange { low: vec![0, 0], high: vec![0xff, 0xff] }],
+            cid_ranges: vec![(0, 0xffff, 0)],
+            use_cmap: None,
+        }
+    }
+    /// Look up a predefined CMap by name, like `UniGB-UCS2-H`.
+    ///
+    /// `Identity-H` and `Identity-V` are built in. No other CMap data is bundled with this crate:
+    /// the rest are read from `dir`, which should contain the files of Adobe's cmap-resources
+    /// (one file per CMap, named after it). `usecmap` is followed.
+    pub fn predefined(name: &str, dir: Option<&Path>) -> Option<CMap> {
+        CMap::predefined_depth(name, dir, 8)
+    }
+    fn predefined_depth(name: &str, dir: Option<&Path>, depth: usize) -> Option<CMap> {
+        match name {
+            "Identity-H" => return Some(CMap::identity(false)),
+            "Identity-V" => return Some(CMap::identity(true)),
+            _ => {}
+        }
+        if depth == 0 || name.contains(['/', '\\']) || name.starts_with('.') {
+            return None;
+        }
+        let data = std::fs::read(dir?.join(name)).ok()?;
+        let mut cmap = match CMap::parse(&data) {
+            Ok(cmap) => cmap,
+            Err(e) => {
+                warn!("failed to parse CMap {}: {}", name, e);
+                return None;
+            }
+        };
+        if let Some(parent) = cmap.use_cmap.as_deref().and_then(|n| CMap::predefined_depth(n, dir, depth - 1)) {
+            cmap.merge_parent(parent);
+        }
+        Some(cmap)
+    }
+    /// Prepend the mappings of a `usecmap` parent, so that our own entries take precedence.
+    pub fn merge_parent(&mut self, parent: CMap) {
+        let CMap { codespace, mut cid_ranges, .. } = parent;
+        for range in codespace {
+            if !self.codespace.iter().any(|r| r.low == range.low && r.high == range.high) {
+                self.codespace.push(range);
+            }
+        }
+        cid_ranges.append(&mut self.cid_ranges);
+        self.cid_ranges = cid_ranges;
+        self.use_cmap = None;
+    }
+    pub fn parse(data: &[u8]) -> Result<CMap> {
+        let mut lexer = Lexer::new(data);
+        let mut cmap = CMap::default();
+        let string = |lexer: &mut Lexer| -> Option<Vec<u8>> {
+            match parse_with_lexer(lexer, &NoResolve, ParseFlags::STRING) {
+                Ok(Primitive::String(s)) => Some(s.as_bytes().to_vec()),
+                _ => None
+            }
+        };
+        let integer = |lexer: &mut Lexer| -> Option<u32> {
+            match parse_with_lexer(lexer, &NoResolve, ParseFlags::INTEGER) {
+                Ok(Primitive::Integer(i)) => u32::try_from(i).ok(),
+                _ => None
+            }
+        };
+        let mut last_name = None;
+        while let Ok(substr) = lexer.next() {
+            match substr.as_slice() {
+                b"/CMapName" => {
+                    if let Ok(name) = lexer.next() {
+                        cmap.name = name.as_str().ok().and_then(|s| s.strip_prefix('/')).map(|s| s.into());
+                    }
+                }
+                b"/WMode" => cmap.vertical = integer(&mut lexer) == Some(1),
+                b"begincodespacerange" => {
+                    while let (Some(low), Some(high)) = (string(&mut lexer), string(&mut lexer)) {
+                        if low.len() != high.len() || low.is_empty() || low.len() > 4 {
+                            bail!("invalid codespace range {:?} {:?}", low, high);
+                        }
+                        cmap.codespace.push(CodespaceRange { low, high });
+                    }
+                }
+                b"begincidrange" => {
+                    while let (Some(low), Some(high), Some(cid)) = (string(&mut lexer), string(&mut lexer), integer(&mut lexer)) {
+                        cmap.cid_ranges.push((code_value(&low), code_value(&high), cid));
+                    }
+                }
+                b"begincidchar" => {
+                    while let (Some(code), Some(cid)) = (string(&mut lexer), integer(&mut lexer)) {
+                        let code = code_value(&code);
+                        cmap.cid_ranges.push((code, code, cid));
+                    }
+                }
+                b"usecmap" => cmap.use_cmap = last_name.take(),
+                b"endcmap" => break,
+                s if s.starts_with(b"/") => {
+                    last_name = substr.as_str().ok().map(|s| s[1..].to_string());
+                    continue;
+                }
+                _ => {}
+            }
+            last_name = None;
         }
+        Ok(cmap)
+    }
+
+    /// Split the next character code off `data`, returning the code and its length in bytes.
+    ///
+    /// Bytes that match no codespace range are consumed in units of the shortest range.
+    pub fn next_code(&self, data: &[u8]) -> Option<(u32, usize)> {
+        if data.is_empty() {
+            return None;
+        }
+        for n in 1 ..= data.len().min(4) {
+            if self.codespace.iter().any(|r| r.matches(&data[..n])) {
+                return Some((code_value(&data[..n]), n));
+            }
+        }
+        let n = self.codespace.iter().map(|r| r.low.len()).min().unwrap_or(1).min(data.len());
+        Some((code_value(&data[..n]), n))
+    }
+
+    /// Word spacing (`Tw`) applies to the single byte code 32 only, never to a multi-byte code like `<0020>`.
+    pub fn is_word_space(code: u32, len: usize) -> bool {
+        code == 32 && len == 1
+    }
+
+    /// Iterate over the character codes of a show-text string.
+    pub fn codes<'a>(&'a self, mut data: &'a [u8]) -> impl Iterator<Item=u32> + 'a {
+        std::iter::from_fn(move || {
+            let (code, n) = self.next_code(data)?;
+            data = &data[n..];
+            Some(code)
+        })
+    }
+
+    /// The CID for `code`. Later entries take precedence.
+    pub fn cid(&self, code: u32) -> Option<u32> {
+        self.cid_ranges.iter().rev()
+            .find(|&&(low, high, _)| low <= code && code <= high)
+            .map(|&(low, _, cid)| cid + (code - low))
+    }
+}
+
+/// Map a glyph name to its Unicode character, following the Adobe Glyph List conventions.
+///
+/// Handles `uniXXXX` and `uXXXX[XX]` names, suffixed names like `a.sc`,
+/// and the names used by the standard Latin encodings.
+pub fn glyph_name_to_unicode(name: &str) -> Option<char> {
+    let name = name.split('.').next().unwrap_or(name);
+    if let Some(hex) = name.strip_prefix("uni") {
+        if hex.len() == 4 {
+            return u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32);
+        }
+    }
+    if let Some(hex) = name.strip_prefix('u') {
+        if (4..=6).contains(&hex.len()) {
+            if let Some(c) = u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32) {
+                return Some(c);
+            }
+        }
+    }
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphabetic() {
+            return Some(c);
+        }
+    }
+    let c = match name {
+        "space" => ' ',
+        "exclam" => '!',
+        "quotedbl" => '"',
+        "numbersign" => '#',
+        "dollar" => '$',
+        "percent" => '%',
+        "ampersand" => '&',
+        "quotesingle" => '\'',
+        "parenleft" => '(',
+        "parenright" => ')',
+        "asterisk" => '*',
+        "plus" => '+',
+        "comma" => ',',
+        "hyphen" => '-',
+        "period" => '.',
+        "slash" => '/',
+        "zero" => '0',
+        "one" => '1',
+        "two" => '2',
+        "three" => '3',
+        "four" => '4',
+        "five" => '5',
+        "six" => '6',
+        "seven" => '7',
+        "eight" => '8',
+        "nine" => '9',
+        "colon" => ':',
+        "semicolon" => ';',
+        "less" => '<',
+        "equal" => '=',
+        "greater" => '>',
+        "question" => '?',
+        "at" => '@',
+        "bracketleft" => '[',
+        "backslash" => '\\',
+        "bracketright" => ']',
+        "asciicircum" => '^',
+        "underscore" => '_',
+        "grave" => '`',
+        "braceleft" => '{',
+        "bar" => '|',
+        "braceright" => '}',
+        "asciitilde" => '~',
+        "quoteleft" => '\u{2018}',
+        "quoteright" => '\u{2019}',
+        "quotedblleft" => '\u{201C}',
+        "quotedblright" => '\u{201D}',
+        "quotesinglbase" => '\u{201A}',
+        "quotedblbase" => '\u{201E}',
+        "endash" => '\u{2013}',
+        "emdash" => '\u{2014}',
+        "bullet" => '\u{2022}',
+        "ellipsis" => '\u{2026}',
+        "dagger" => '\u{2020}',
+        "daggerdbl" => '\u{2021}',
+        "perthousand" => '\u{2030}',
+        "guilsinglleft" => '\u{2039}',
+        "guilsinglright" => '\u{203A}',
+        "trademark" => '\u{2122}',
+        "Euro" => '\u{20AC}',
+        "fi" => '\u{FB01}',
+        "fl" => '\u{FB02}',
+        "ff" => '\u{FB00}',
+        "ffi" => '\u{FB03}',
+        "ffl" => '\u{FB04}',
+        "nbspace" => '\u{A0}',
+        "exclamdown" => '¡',
+        "cent" => '¢',
+        "sterling" => '£',
+        "currency" => '¤',
+        "yen" => '¥',
+        "brokenbar" => '¦',
+        "section" => '§',
+        "dieresis" => '¨',
+        "copyright" => '©',
+        "ordfeminine" => 'ª',
+        "guillemotleft" => '«',
+        "logicalnot" => '¬',
+        "registered" => '®',
+        "macron" => '¯',
+        "degree" => '°',
+        "plusminus" => '±',
+        "acute" => '´',
+        "mu" => 'µ',
+        "paragraph" => '¶',
+        "periodcentered" => '·',
+        "cedilla" => '¸',
+        "ordmasculine" => 'º',
+        "guillemotright" => '»',
+        "questiondown" => '¿',
+        "multiply" => '×',
+        "divide" => '÷',
+        "germandbls" => 'ß',
+        "AE" => 'Æ',
+        "ae" => 'æ',
+        "OE" => 'Œ',
+        "oe" => 'œ',
+        "Oslash" => 'Ø',
+        "oslash" => 'ø',
+        "dotlessi" => 'ı',
+        "florin" => 'ƒ',
+        "circumflex" => 'ˆ',
+        "tilde" => '˜',
+        _ => return accented(name)
+    };
+    Some(c)
+}
+
+/// Names like `eacute` or `Udieresis`: a base letter followed by a combining mark.
+fn accented(name: &str) -> Option<char> {
+    let mut chars = name.chars();
+    let base = chars.next().filter(|c| c.is_ascii_alphabetic())?;
+    let mark = match chars.as_str() {
+        "grave" => '\u{300}',
+        "acute" => '\u{301}',
+        "circumflex" => '\u{302}',
+        "tilde" => '\u{303}',
+        "dieresis" => '\u{308}',
+        "ring" => '\u{30A}',
+        "caron" => '\u{30C}',
+        "cedilla" => '\u{327}',
+        _ => return None
+    };
+    // precomposed Latin-1 / Latin Extended-A letters
+    const TABLE: &[(char, char, char)] = &[
+        ('A', '\u{300}', 'À'), ('A', '\u{301}', 'Á'), ('A', '\u{302}', 'Â'), ('A', '\u{303}', 'Ã'), ('A', '\u{308}', 'Ä'), ('A', '\u{30A}', 'Å'),
+        ('C', '\u{327}', 'Ç'), ('C', '\u{30C}', 'Č'),
+        ('E', '\u{300}', 'È'), ('E', '\u{301}', 'É'), ('E', '\u{302}', 'Ê'), ('E', '\u{308}', 'Ë'), ('E', '\u{30C}', 'Ě'),
+        ('I', '\u{300}', 'Ì'), ('I', '\u{301}', 'Í'), ('I', '\u{302}', 'Î'), ('I', '\u{308}', 'Ï'),
+        ('N', '\u{303}', 'Ñ'), ('N', '\u{30C}', 'Ň'),
+        ('O', '\u{300}', 'Ò'), ('O', '\u{301}', 'Ó'), ('O', '\u{302}', 'Ô'), ('O', '\u{303}', 'Õ'), ('O', '\u{308}', 'Ö'),
+        ('R', '\u{30C}', 'Ř'), ('S', '\u{30C}', 'Š'), ('Z', '\u{30C}', 'Ž'),
+        ('U', '\u{300}', 'Ù'), ('U', '\u{301}', 'Ú'), ('U', '\u{302}', 'Û'), ('U', '\u{308}', 'Ü'), ('U', '\u{30A}', 'Ů'),
+        ('Y', '\u{301}', 'Ý'), ('Y', '\u{308}', 'Ÿ'),
+        ('a', '\u{300}', 'à'), ('a', '\u{301}', 'á'), ('a', '\u{302}', 'â'), ('a', '\u{303}', 'ã'), ('a', '\u{308}', 'ä'), ('a', '\u{30A}', 'å'),
+        ('c', '\u{327}', 'ç'), ('c', '\u{30C}', 'č'),
+        ('e', '\u{300}', 'è'), ('e', '\u{301}', 'é'), ('e', '\u{302}', 'ê'), ('e', '\u{308}', 'ë'), ('e', '\u{30C}', 'ě'),
+        ('i', '\u{300}', 'ì'), ('i', '\u{301}', 'í'), ('i', '\u{302}', 'î'), ('i', '\u{308}', 'ï'),
+        ('n', '\u{303}', 'ñ'), ('n', '\u{30C}', 'ň'),
+        ('o', '\u{300}', 'ò'), ('o', '\u{301}', 'ó'), ('o', '\u{302}', 'ô'), ('o', '\u{303}', 'õ'), ('o', '\u{308}', 'ö'),
+        ('r', '\u{30C}', 'ř'), ('s', '\u{30C}', 'š'), ('z', '\u{30C}', 'ž'),
+        ('u', '\u{300}', 'ù'), ('u', '\u{301}', 'ú'), ('u', '\u{302}', 'û'), ('u', '\u{308}', 'ü'), ('u', '\u{30A}', 'ů'),
+        ('y', '\u{301}', 'ý'), ('y', '\u{308}', 'ÿ'),
+    ];
+    TABLE.iter().find(|&&(b, m, _)| b == base && m == mark).map(|&(_, _, c)| c)
+}
+
+/// Glyph names of codes 32 to 126 in WinAnsiEncoding and MacRomanEncoding.
+/// StandardEncoding has `quoteright` and `quoteleft` at 39 and 96.
+const ASCII_GLYPHS: [&str; 95] = [
+    "space", "exclam", "quotedbl", "numbersign", "dollar", "percent", "ampersand", "quotesingle", "parenleft",
+    "parenright", "asterisk", "plus", "comma", "hyphen", "period", "slash", "zero", "one", "two", "three",
+    "four", "five", "six", "seven", "eight", "nine", "colon", "semicolon", "less", "equal", "greater",
+    "question", "at", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q",
+    "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "bracketleft", "backslash", "bracketright", "asciicircum",
+    "underscore", "grave", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p",
+    "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "braceleft", "bar", "braceright", "asciitilde",
+];
+
+/// `(code, glyph name)` for codes above 126, by code.
+const STANDARD_GLYPHS: [(u8, &str); 54] = [
+    (161, "exclamdown"), (162, "cent"), (163, "sterling"), (164, "fraction"), (165, "yen"), (166, "florin"),
+    (167, "section"), (168, "currency"), (169, "quotesingle"), (170, "quotedblleft"), (171, "guillemotleft"),
+    (172, "guilsinglleft"), (173, "guilsinglright"), (174, "fi"), (175, "fl"), (177, "endash"),
+    (178, "dagger"), (179, "daggerdbl"), (180, "periodcentered"), (182, "paragraph"), (183, "bullet"),
+    (184, "quotesinglbase"), (185, "quotedblbase"), (186, "quotedblright"), (187, "guillemotright"),
+    (188, "ellipsis"), (189, "perthousand"), (191, "questiondown"), (193, "grave"), (194, "acute"),
+    (195, "circumflex"), (196, "tilde"), (197, "macron"), (198, "breve"), (199, "dotaccent"),
+    (200, "dieresis"), (202, "ring"), (203, "cedilla"), (205, "hungarumlaut"), (206, "ogonek"),
+    (207, "caron"), (208, "emdash"), (225, "AE"), (227, "ordfeminine"), (232, "Lslash"), (233, "Oslash"),
+    (234, "OE"), (235, "ordmasculine"), (241, "ae"), (245, "dotlessi"), (248, "lslash"), (249, "oslash"),
+    (250, "oe"), (251, "germandbls"),
+];
+
+const WIN_ANSI_GLYPHS: [(u8, &str); 123] = [
+    (128, "Euro"), (130, "quotesinglbase"), (131, "florin"), (132, "quotedblbase"), (133, "ellipsis"),
+    (134, "dagger"), (135, "daggerdbl"), (136, "circumflex"), (137, "perthousand"), (138, "Scaron"),
+    (139, "guilsinglleft"), (140, "OE"), (142, "Zcaron"), (145, "quoteleft"), (146, "quoteright"),
+    (147, "quotedblleft"), (148, "quotedblright"), (149, "bullet"), (150, "endash"), (151, "emdash"),
+    (152, "tilde"), (153, "trademark"), (154, "scaron"), (155, "guilsinglright"), (156, "oe"),
+    (158, "zcaron"), (159, "Ydieresis"), (160, "space"), (161, "exclamdown"), (162, "cent"),
+    (163, "sterling"), (164, "currency"), (165, "yen"), (166, "brokenbar"), (167, "section"),
+    (168, "dieresis"), (169, "copyright"), (170, "ordfeminine"), (171, "guillemotleft"), (172, "logicalnot"),
+    (173, "hyphen"), (174, "registered"), (175, "macron"), (176, "degree"), (177, "plusminus"),
+    (178, "twosuperior"), (179, "threesuperior"), (180, "acute"), (181, "mu"), (182, "paragraph"),
+    (183, "periodcentered"), (184, "cedilla"), (185, "onesuperior"), (186, "ordmasculine"),
+    (187, "guillemotright"), (188, "onequarter"), (189, "onehalf"), (190, "threequarters"),
+    (191, "questiondown"), (192, "Agrave"), (193, "Aacute"), (194, "Acircumflex"), (195, "Atilde"),
+    (196, "Adieresis"), (197, "Aring"), (198, "AE"), (199, "Ccedilla"), (200, "Egrave"), (201, "Eacute"),
+    (202, "Ecircumflex"), (203, "Edieresis"), (204, "Igrave"), (205, "Iacute"), (206, "Icircumflex"),
+    (207, "Idieresis"), (208, "Eth"), (209, "Ntilde"), (210, "Ograve"), (211, "Oacute"), (212, "Ocircumflex"),
+    (213, "Otilde"), (214, "Odieresis"), (215, "multiply"), (216, "Oslash"), (217, "Ugrave"), (218, "Uacute"),
+    (219, "Ucircumflex"), (220, "Udieresis"), (221, "Yacute"), (222, "Thorn"), (223, "germandbls"),
+    (224, "agrave"), (225, "aacute"), (226, "acircumflex"), (227, "atilde"), (228, "adieresis"),
+    (229, "aring"), (230, "ae"), (231, "ccedilla"), (232, "egrave"), (233, "eacute"), (234, "ecircumflex"),
+    (235, "edieresis"), (236, "igrave"), (237, "iacute"), (238, "icircumflex"), (239, "idieresis"),
+    (240, "eth"), (241, "ntilde"), (242, "ograve"), (243, "oacute"), (244, "ocircumflex"), (245, "otilde"),
+    (246, "odieresis"), (247, "divide"), (248, "oslash"), (249, "ugrave"), (250, "uacute"),
+    (251, "ucircumflex"), (252, "udieresis"), (253, "yacute"), (254, "thorn"), (255, "ydieresis"),
+];
+
+const MAC_ROMAN_GLYPHS: [(u8, &str); 113] = [
+    (128, "Adieresis"), (129, "Aring"), (130, "Ccedilla"), (131, "Eacute"), (132, "Ntilde"),
+    (133, "Odieresis"), (134, "Udieresis"), (135, "aacute"), (136, "agrave"), (137, "acircumflex"),
+    (138, "adieresis"), (139, "atilde"), (140, "aring"), (141, "ccedilla"), (142, "eacute"), (143, "egrave"),
+    (144, "ecircumflex"), (145, "edieresis"), (146, "iacute"), (147, "igrave"), (148, "icircumflex"),
+    (149, "idieresis"), (150, "ntilde"), (151, "oacute"), (152, "ograve"), (153, "ocircumflex"),
+    (154, "odieresis"), (155, "otilde"), (156, "uacute"), (157, "ugrave"), (158, "ucircumflex"),
+    (159, "udieresis"), (160, "dagger"), (161, "degree"), (162, "cent"), (163, "sterling"), (164, "section"),
+    (165, "bullet"), (166, "paragraph"), (167, "germandbls"), (168, "registered"), (169, "copyright"),
+    (170, "trademark"), (171, "acute"), (172, "dieresis"), (174, "AE"), (175, "Oslash"), (177, "plusminus"),
+    (180, "yen"), (181, "mu"), (187, "ordfeminine"), (188, "ordmasculine"), (190, "ae"), (191, "oslash"),
+    (192, "questiondown"), (193, "exclamdown"), (194, "logicalnot"), (196, "florin"), (199, "guillemotleft"),
+    (200, "guillemotright"), (201, "ellipsis"), (202, "space"), (203, "Agrave"), (204, "Atilde"),
+    (205, "Otilde"), (206, "OE"), (207, "oe"), (208, "endash"), (209, "emdash"), (210, "quotedblleft"),
+    (211, "quotedblright"), (212, "quoteleft"), (213, "quoteright"), (214, "divide"), (216, "ydieresis"),
+    (217, "Ydieresis"), (218, "fraction"), (219, "currency"), (220, "guilsinglleft"), (221, "guilsinglright"),
+    (222, "fi"), (223, "fl"), (224, "daggerdbl"), (225, "periodcentered"), (226, "quotesinglbase"),
+    (227, "quotedblbase"), (228, "perthousand"), (229, "Acircumflex"), (230, "Ecircumflex"), (231, "Aacute"),
+    (232, "Edieresis"), (233, "Egrave"), (234, "Iacute"), (235, "Icircumflex"), (236, "Idieresis"),
+    (237, "Igrave"), (238, "Oacute"), (239, "Ocircumflex"), (241, "Ograve"), (242, "Uacute"),
+    (243, "Ucircumflex"), (244, "Ugrave"), (245, "dotlessi"), (246, "circumflex"), (247, "tilde"),
+    (248, "macron"), (249, "breve"), (250, "dotaccent"), (251, "ring"), (252, "cedilla"),
+    (253, "hungarumlaut"), (254, "ogonek"), (255, "caron"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::NoResolve;
+
+    #[test]
+    fn test_cmap() {
+        let data = b"/CIDInit /ProcSet findresource begin 12 dict begin begincmap
+            /CMapName /Test-H def /WMode 0 def
+            2 begincodespacerange <00> <80> <8140> <FEFE> endcodespacerange
+            2 begincidrange <20> <7e> 1 <8140> <817e> 633 endcidrange
+            1 begincidchar <8141> 9000 endcidchar
+            endcmap CMapName currentdict /CMap defineresource pop end end";
+        let cmap = CMap::parse(data).unwrap();
+        assert_eq!(cmap.name.as_deref(), Some("Test-H"));
+        assert!(!cmap.vertical);
+        assert_eq!(cmap.codespace.len(), 2);
+
+        let codes: Vec<u32> = cmap.codes(b"A\x81\x40B\x81\x41").collect();
+        assert_eq!(codes, [0x41, 0x8140, 0x42, 0x8141]);
+        let cids: Vec<_> = codes.iter().map(|&c| cmap.cid(c)).collect();
+        assert_eq!(cids, [Some(34), Some(633), Some(35), Some(9000)]);
+    }
+
+    #[test]
+    fn test_word_space() {
+        let spaces = |cmap: &CMap, mut data: &[u8]| {
+            let mut n = 0;
+            while let Some((code, len)) = cmap.next_code(data) {
+                n += CMap::is_word_space(code, len) as usize;
+                data = &data[len..];
+            }
+            n
+        };
+        assert_eq!(spaces(&CMap::identity(false), b"\x00\x20\x20\x00\x00\x41"), 0);
+
+        let mixed = CMap::parse(b"begincmap 2 begincodespacerange <00> <80> <8140> <fefe> endcodespacerange endcmap").unwrap();
+        assert_eq!(spaces(&mixed, b"a b\x81\x40 "), 2);
+    }
+
+    #[test]
+    fn test_use_cmap() {
+        let parent = CMap::parse(b"begincmap 1 begincodespacerange <00> <ff> endcodespacerange \
+            1 begincidrange <00> <ff> 100 endcidrange endcmap").unwrap();
+        let mut child = CMap::parse(b"begincmap /Parent usecmap \
+            1 begincidchar <41> 7 endcidchar endcmap").unwrap();
+        assert_eq!(child.use_cmap.as_deref(), Some("Parent"));
+        child.merge_parent(parent);
+        assert_eq!(child.cid(0x41), Some(7));
+        assert_eq!(child.cid(0x42), Some(166));
+        assert_eq!(child.codes(b"AB").count(), 2);
+    }
+
+    #[test]
+    fn test_predefined_cmap() {
+        let dir = std::env::temp_dir().join(format!("pdf-cmaps-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Test-Base"), b"begincmap 1 begincodespacerange <00> <ff> endcodespacerange \
+            1 begincidrange <20> <7e> 1 endcidrange endcmap").unwrap();
+        std::fs::write(dir.join("Test-H"), b"begincmap /Test-Base usecmap \
+            1 begincidchar <41> 500 endcidchar endcmap").unwrap();
+
+        let cmap = CMap::predefined("Test-H", Some(&dir)).unwrap();
+        assert_eq!(cmap.cid(0x41), Some(500));
+        assert_eq!(cmap.cid(0x42), Some(35));
+        assert!(CMap::predefined("Test-H", None).is_none());
+        assert!(CMap::predefined("../Test-H", Some(&dir)).is_none());
+        assert!(CMap::predefined("Identity-V", None).unwrap().vertical);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_identity_cmap() {
+        let cmap = CMap::identity(true);
+        assert!(cmap.vertical);
+        let codes: Vec<u32> = cmap.codes(&[0x01, 0x02, 0xff, 0x00]).collect();
+        assert_eq!(codes, [0x0102, 0xff00]);
+        assert_eq!(cmap.cid(0x0102), Some(0x0102));
+    }
+
+    #[test]
+    fn test_identity_encodings() {
+        let h = Encoding::from_primitive(Primitive::name("Identity-H"), &NoResolve).unwrap();
+        assert_eq!(h.base, BaseEncoding::IdentityH);
+        let v = Encoding::from_primitive(Primitive::name("Identity-V"), &NoResolve).unwrap();
+        assert_eq!(v.base, BaseEncoding::IdentityV);
+
+        // Differences are kept alongside an Identity base, and malformed entries are skipped
+        let data = b"<< /BaseEncoding /Identity-H /Differences [1 /a (junk) /b] >>";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let e = Encoding::from_primitive(p, &NoResolve).unwrap();
+        assert!(e.cmap().is_some());
+        assert_eq!(e.differences.get(&1).map(|n| n.as_str()), Some("a"));
+        assert_eq!(e.differences.get(&2).map(|n| n.as_str()), Some("b"));
+        assert!(Encoding::from_primitive(Primitive::Integer(1), &NoResolve).is_err());
+    }
+
+    #[test]
+    fn test_base_encoding_glyph_names() {
+        assert_eq!(BaseEncoding::StandardEncoding.glyph_name(39), Some("quoteright"));
+        assert_eq!(BaseEncoding::WinAnsiEncoding.glyph_name(39), Some("quotesingle"));
+        assert_eq!(BaseEncoding::StandardEncoding.glyph_name(0xe1), Some("AE"));
+        assert_eq!(BaseEncoding::WinAnsiEncoding.glyph_name(0xe9), Some("eacute"));
+        assert_eq!(BaseEncoding::WinAnsiEncoding.glyph_name(0x81), None);
+        assert_eq!(BaseEncoding::MacRomanEncoding.glyph_name(0x8e), Some("eacute"));
+        assert_eq!(BaseEncoding::SymbolEncoding.glyph_name(b'p'), Some("pi"));
+        assert_eq!(BaseEncoding::IdentityH.glyph_name(b'a'), None);
+
+        let mut encoding = Encoding::standard();
+        encoding.differences.insert(65, "Aring".into());
+        assert_eq!(encoding.glyph_name(65), Some("Aring"));
+        assert_eq!(encoding.glyph_name(66), Some("B"));
+        assert_eq!(encoding.glyph_name(300), None);
+    }
+
+    #[test]
+    fn test_glyph_name_to_unicode() {
+        assert_eq!(glyph_name_to_unicode("A"), Some('A'));
+        assert_eq!(glyph_name_to_unicode("a.sc"), Some('a'));
+        assert_eq!(glyph_name_to_unicode("uni20AC"), Some('€'));
+        assert_eq!(glyph_name_to_unicode("u1F600"), Some('\u{1F600}'));
+        assert_eq!(glyph_name_to_unicode("seven"), Some('7'));
+        assert_eq!(glyph_name_to_unicode("fi"), Some('\u{FB01}'));
+        assert_eq!(glyph_name_to_unicode("eacute"), Some('é'));
+        assert_eq!(glyph_name_to_unicode("Scaron"), Some('Š'));
+        assert_eq!(glyph_name_to_unicode("g123"), None);
     }
 }
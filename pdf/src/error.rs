@@ -11,7 +11,7 @@ pub enum PdfError {
     #[snafu(display("Unexpected end of file"))]
     EOF,
 
-    #[snafu(display("Shared"))]
+    #[snafu(display("{}", source))]
     Shared { source: Arc<PdfError> },
 
     #[snafu(display("Not enough Operator arguments"))]
@@ -83,6 +83,9 @@ pub enum PdfError {
         found: String,
     },
 
+    #[snafu(display("{} /{} is not in the resource dictionary.", kind, name))]
+    MissingResource { kind: &'static str, name: String },
+
     #[snafu(display("Expected dictionary /Type = {}. Found /Type = {}.", expected, found))]
     WrongDictionaryType {expected: String, found: String},
 
@@ -123,6 +126,9 @@ pub enum PdfError {
     #[snafu(display("JPEG"))]
     Jpeg { source: jpeg_decoder::Error },
 
+    #[snafu(display("No {} decoder is available, register one with `pdf::enc::{}`.", filter, setter))]
+    MissingDecoder { filter: &'static str, setter: &'static str },
+
     #[snafu(display("IO Error"))]
     Io { source: io::Error },
 
@@ -153,6 +159,9 @@ pub enum PdfError {
     #[snafu(display("Max nesting depth reached"))]
     MaxDepth,
 
+    #[snafu(display("Object {} contains a reference to itself.", obj_nr))]
+    RecursiveReference { obj_nr: ObjNr },
+
     #[snafu(display("Invalid"))]
     Invalid,
 }
@@ -160,6 +169,14 @@ impl PdfError {
     pub fn trace(&self) {
         trace(self, 0);
     }
+    /// The error without the `Try`, `FromPrimitive` and `Shared` wrappers added while propagating it.
+    pub fn root_cause(&self) -> &PdfError {
+        match self {
+            PdfError::Try { ref source, .. } | PdfError::FromPrimitive { ref source, .. } => source.root_cause(),
+            PdfError::Shared { ref source } => source.root_cause(),
+            e => e
+        }
+    }
     pub fn is_eof(&self) -> bool {
         match self {
             PdfError::EOF => true,
@@ -2,6 +2,7 @@
 use std::fs;
 use std::marker::PhantomData;
 use std::collections::HashMap;
+use std::cell::RefCell;
 use std::sync::{Arc};
 use std::path::Path;
 use std::io::Write;
@@ -80,6 +81,23 @@ impl<B: Backend> Storage<B> {
         Ok(data.into())
     }
 }
+thread_local! {
+    /// objects being parsed by `Storage::get` on this thread, as (storage address, reference)
+    static PARSING: RefCell<Vec<(usize, PlainRef)>> = const { RefCell::new(Vec::new()) };
+}
+struct ParsingGuard;
+impl ParsingGuard {
+    fn push(entry: (usize, PlainRef)) -> Self {
+        PARSING.with(|stack| stack.borrow_mut().push(entry));
+        ParsingGuard
+    }
+}
+impl Drop for ParsingGuard {
+    fn drop(&mut self) {
+        PARSING.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
 impl<B: Backend> Resolve for Storage<B> {
     fn resolve_flags(&self, r: PlainRef, flags: ParseFlags, depth: usize) -> Result<Primitive> {
         match self.changes.get(&r.id) {
@@ -111,8 +129,22 @@ impl<B: Backend> Resolve for Storage<B> {
 
     fn get<T: Object+DataSize>(&self, r: Ref<T>) -> Result<RcRef<T>> {
         let key = r.get_inner();
-        
+
+        // An object that is being parsed on this thread would wait for itself in the cache.
+        let entry = (self as *const Self as usize, key);
+        let (depth, recursive) = PARSING.with(|stack| {
+            let stack = stack.borrow();
+            (stack.len(), stack.contains(&entry))
+        });
+        if recursive {
+            return Err(PdfError::RecursiveReference { obj_nr: key.id });
+        }
+        if depth >= self.options.max_nesting_depth {
+            return Err(PdfError::MaxDepth);
+        }
+
         let res = self.cache.get(key, || {
+            let _guard = ParsingGuard::push(entry);
             match self.resolve(key).and_then(|p| T::from_primitive(p, self)) {
                 Ok(obj) => Ok(Shared::new(obj).into()),
                 Err(e) => Err(Arc::new(e)),
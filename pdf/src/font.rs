@@ -2,7 +2,8 @@ use crate as pdf;
 use crate::object::*;
 use crate::primitive::*;
 use crate::error::*;
-use crate::encoding::Encoding;
+use crate::encoding::{Encoding, BaseEncoding, CMap};
+use crate::content::{Matrix, Op, parse_ops};
 use std::collections::HashMap;
 use crate::parser::{Lexer, parse_with_lexer, ParseFlags};
 use std::convert::TryInto;
@@ -10,6 +11,8 @@ use std::sync::Arc;
 use istring::SmallString;
 use datasize::DataSize;
 
+pub(crate) mod metrics;
+
 #[allow(non_upper_case_globals, dead_code)]
 mod flags {
     pub const FixedPitch: u32    = 1 << 0;
@@ -55,6 +58,7 @@ pub enum FontData {
     TrueType(TFont),
     CIDFontType0(CIDFont),
     CIDFontType2(CIDFont),
+    Type3(Type3Font),
     Other(Dictionary),
     None,
 }
@@ -114,6 +118,7 @@ impl Object for Font {
             FontType::TrueType => FontData::TrueType(TFont::from_dict(dict, resolve)?),
             FontType::CIDFontType0 => FontData::CIDFontType0(CIDFont::from_dict(dict, resolve)?),
             FontType::CIDFontType2 => FontData::CIDFontType2(CIDFont::from_dict(dict, resolve)?),
+            FontType::Type3 => FontData::Type3(Type3Font::from_dict(dict, resolve)?),
             _ => FontData::Other(dict)
         };
 
@@ -198,6 +203,25 @@ impl Widths {
         self.values[cid - self.first_char] = width;
     }
 }
+/// Vertical metrics of a CID font, from `/W2` and `/DW2`.
+#[derive(Debug)]
+pub struct VerticalMetrics {
+    /// `(w1_y, v_x, v_y)` by CID
+    values: HashMap<usize, (f32, f32, f32)>,
+    /// `[v_y w1_y]` from `/DW2`
+    default: (f32, f32),
+}
+impl VerticalMetrics {
+    /// `(w1_y, v_x, v_y)` for `cid`: the vertical advance and the position vector from the
+    /// horizontal to the vertical origin. `w0` is the horizontal width, which gives the default `v_x`.
+    pub fn get(&self, cid: usize, w0: f32) -> (f32, f32, f32) {
+        match self.values.get(&cid) {
+            Some(&m) => m,
+            None => (self.default.1, w0 / 2., self.default.0)
+        }
+    }
+}
+
 impl Font {
     pub fn embedded_data(&self, resolve: &impl Resolve) -> Option<Result<Arc<[u8]>>> {
         match self.data {
@@ -207,6 +231,27 @@ impl Font {
             _ => None
         }
     }
+    /// Whether the file contains the glyphs (a font program, or the procedures of a Type3 font).
+    pub fn is_embedded(&self) -> bool {
+        match self.data {
+            FontData::Type0(ref t) => t.descendant_fonts.first().is_some_and(|f| f.is_embedded()),
+            FontData::CIDFontType0(ref c) | FontData::CIDFontType2(ref c) => c.font_descriptor.is_embedded(),
+            FontData::Type1(ref t) | FontData::TrueType(ref t) => t.font_descriptor.as_ref().is_some_and(|d| d.is_embedded()),
+            FontData::Type3(_) => true,
+            FontData::Other(_) | FontData::None => false,
+        }
+    }
+    /// Summary of the font for listing, see `Resources::font_info`.
+    pub fn font_info(&self, resource_name: Option<Name>) -> FontInfo {
+        FontInfo {
+            resource_name,
+            base_font: self.name.clone(),
+            subtype: self.subtype,
+            embedded: self.is_embedded(),
+            cid: self.is_cid(),
+            encoding: self.encoding.as_ref().map(|e| e.base.clone()),
+        }
+    }
     pub fn is_cid(&self) -> bool {
         matches!(self.data, FontData::Type0(_) | FontData::CIDFontType0(_) | FontData::CIDFontType2(_))
     }
@@ -217,6 +262,23 @@ impl Font {
             _ => None
         }
     }
+    /// The CMap of a Type0 font, mapping codes in show-text strings to CIDs.
+    /// 
+    /// This is the embedded CMap stream if `/Encoding` is one, or the predefined CMap it names.
+    pub fn cmap(&self) -> Option<&CMap> {
+        match self.data {
+            FontData::Type0(_) => self.encoding.as_ref()?.cmap(),
+            _ => None
+        }
+    }
+    /// Whether text in this font is laid out top to bottom (`/WMode 1`).
+    pub fn is_vertical(&self) -> bool {
+        match self.encoding {
+            Some(Encoding { base: BaseEncoding::IdentityV, .. }) => true,
+            Some(Encoding { cmap: Some(ref cmap), .. }) => cmap.vertical,
+            _ => false
+        }
+    }
     pub fn encoding(&self) -> Option<&Encoding> {
         self.encoding.as_ref()
     }
@@ -229,17 +291,26 @@ impl Font {
     }
     pub fn widths(&self, resolve: &impl Resolve) -> Result<Option<Widths>> {
         match self.data {
-            FontData::Type0(ref t0) => t0.descendant_fonts[0].widths(resolve),
+            FontData::Type0(ref t0) => match t0.descendant_fonts.first() {
+                Some(font) => font.widths(resolve),
+                None => Ok(None)
+            },
             FontData::Type1(ref info) | FontData::TrueType(ref info) => {
                 match *info {
-                    TFont { first_char: Some(first), ref widths, .. } => Ok(Some(Widths {
-                        default: 0.0,
+                    TFont { first_char: Some(first), ref widths, ref font_descriptor, .. } => Ok(Some(Widths {
+                        default: font_descriptor.as_ref().map(|d| d.missing_width).unwrap_or(0.0),
                         first_char: first as usize,
                         values: widths.clone()
                     })),
+                    TFont { base_font: Some(ref name), .. } => Ok(standard_font_widths(name, self.encoding())),
                     _ => Ok(None)
                 }
             },
+            FontData::Type3(Type3Font { first_char: Some(first), ref widths, .. }) => Ok(Some(Widths {
+                default: 0.0,
+                first_char: first as usize,
+                values: widths.clone()
+            })),
             FontData::CIDFontType0(ref cid) | FontData::CIDFontType2(ref cid) => {
                 let mut widths = Widths::new(cid.default_width);
                 let mut iter = cid.widths.iter();
@@ -247,7 +318,7 @@ impl Font {
                     let c1 = p.as_usize()?;
                     match iter.next() {
                         Some(&Primitive::Array(ref array)) => {
-                            widths.ensure_cid(c1 + array.len() - 1);
+                            widths.ensure_cid(c1 + array.len().saturating_sub(1));
                             for (i, w) in array.iter().enumerate() {
                                 widths.set(c1 + i, w.as_number()?);
                             }
@@ -255,7 +326,7 @@ impl Font {
                         Some(&Primitive::Reference(r)) => {
                             match resolve.resolve(r)? {
                                 Primitive::Array(array) => {
-                                    widths.ensure_cid(c1 + array.len() - 1);
+                                    widths.ensure_cid(c1 + array.len().saturating_sub(1));
                                     for (i, w) in array.iter().enumerate() {
                                         widths.set(c1 + i, w.as_number()?);
                                     }
@@ -277,6 +348,43 @@ impl Font {
             _ => Ok(None)
         }
     }
+    /// The vertical metrics of a CID font, for vertical writing.
+    pub fn vertical_metrics(&self, resolve: &impl Resolve) -> Result<Option<VerticalMetrics>> {
+        let cid = match self.data {
+            FontData::Type0(ref t0) => return match t0.descendant_fonts.first() {
+                Some(font) => font.vertical_metrics(resolve),
+                None => Ok(None)
+            },
+            FontData::CIDFontType0(ref cid) | FontData::CIDFontType2(ref cid) => cid,
+            _ => return Ok(None)
+        };
+        let default = match cid.default_vertical[..] {
+            [vy, w1y] => (vy, w1y),
+            ref dw2 => bail!("invalid DW2 {:?}", dw2)
+        };
+        let mut values = HashMap::new();
+        let mut iter = cid.vertical_widths.iter();
+        while let Some(p) = iter.next() {
+            let c1 = p.as_usize()?;
+            match iter.next() {
+                Some(p @ (Primitive::Array(_) | Primitive::Reference(_))) => {
+                    let array = p.clone().resolve(resolve)?.into_array()?;
+                    for (i, m) in array.chunks_exact(3).enumerate() {
+                        values.insert(c1 + i, (m[0].as_number()?, m[1].as_number()?, m[2].as_number()?));
+                    }
+                }
+                Some(&Primitive::Integer(c2)) => {
+                    let mut number = || -> Result<f32> { try_opt!(iter.next()).as_number() };
+                    let m = (number()?, number()?, number()?);
+                    for c in c1 ..= c2 as usize {
+                        values.insert(c, m);
+                    }
+                }
+                p => bail!("unexpected primitive in W2 array: {:?}", p)
+            }
+        }
+        Ok(Some(VerticalMetrics { values, default }))
+    }
     pub fn to_unicode(&self, resolve: &impl Resolve) -> Option<Result<ToUnicodeMap>> {
         self.to_unicode.as_ref().map(|s| s.data(resolve).and_then(|d| parse_cmap(&d)))
     }
@@ -301,6 +409,67 @@ pub struct TFont {
     pub font_descriptor: Option<FontDescriptor>
 }
 
+/// A font whose glyphs are defined by content streams.
+/// 
+/// Glyph space is mapped to text space by `font_matrix`. Unlike other fonts,
+/// `widths` are given in glyph space.
+#[derive(Object, Debug, DataSize)]
+pub struct Type3Font {
+    /// may be all zero, in which case no assumptions about the glyph extents should be made
+    #[pdf(key="FontBBox", default="Rect { left: 0., bottom: 0., right: 0., top: 0. }")]
+    pub font_bbox: Rect,
+
+    #[pdf(key="FontMatrix")]
+    pub font_matrix: Matrix,
+
+    /// glyph name -> glyph description
+    #[pdf(key="CharProcs")]
+    pub char_procs: HashMap<Name, Ref<Stream<()>>>,
+
+    /// raw `/Resources` entry, see `Type3Font::resources`
+    #[pdf(key="Resources")]
+    pub resources: Option<Primitive>,
+
+    #[pdf(key="FirstChar")]
+    pub first_char: Option<i32>,
+
+    #[pdf(key="LastChar")]
+    pub last_char: Option<i32>,
+
+    #[pdf(key="Widths")]
+    pub widths: Vec<f32>,
+
+    #[pdf(key="FontDescriptor")]
+    pub font_descriptor: Option<FontDescriptor>,
+}
+impl Type3Font {
+    /// The resources used by the glyph descriptions.
+    /// If absent, the resources of the page using the font apply.
+    pub fn resources(&self, resolve: &impl Resolve) -> Option<Result<MaybeRef<Resources>>> {
+        let p = self.resources.clone()?;
+        Some(MaybeRef::from_primitive(p, resolve))
+    }
+    /// Parse the glyph description for the glyph `name`.
+    pub fn char_proc(&self, name: &str, resolve: &impl Resolve) -> Option<Result<Vec<Op>>> {
+        let r = *self.char_procs.get(name)?;
+        Some(resolve.get(r).and_then(|s| {
+            let data = Stream::data(&s, resolve)?;
+            parse_ops(&data, resolve)
+        }))
+    }
+    /// The advance of a glyph in text space, given the ops of its description:
+    /// the width set by `d0`/`d1`, mapped through `/FontMatrix`. Also returns the `d1` bounding box in glyph space.
+    pub fn char_width(&self, ops: &[Op]) -> Option<((f32, f32), Option<Rect>)> {
+        ops.iter().find_map(|op| match *op {
+            Op::CharWidth { wx, wy, bbox } => {
+                let m = &self.font_matrix;
+                Some(((m.a * wx + m.c * wy, m.b * wx + m.d * wy), bbox))
+            }
+            _ => None
+        })
+    }
+}
+
 #[derive(Object, Debug, DataSize)]
 pub struct Type0Font {
     #[pdf(key="DescendantFonts")]
@@ -327,6 +496,14 @@ pub struct CIDFont {
     #[pdf(key="CIDToGIDMap")]
     pub cid_to_gid_map: Option<CidToGidMap>,
 
+    /// default vertical metrics `[v_y w1_y]` for vertical writing
+    #[pdf(key="DW2", default="vec![880., -1000.]")]
+    pub default_vertical: Vec<f32>,
+
+    /// vertical metrics, same layout as `/W` but with `w1_y v_x v_y` triples
+    #[pdf(key="W2")]
+    pub vertical_widths: Vec<Primitive>,
+
     #[pdf(other)]
     _other: Dictionary
 }
@@ -398,7 +575,29 @@ pub struct FontDescriptor {
     #[pdf(key="CharSet")]
     pub char_set: Option<PdfString>
 }
+bitflags::bitflags! {
+    /// The `/Flags` entry of a font descriptor.
+    pub struct FontFlags: u32 {
+        const FIXED_PITCH = 1 << 0;
+        const SERIF = 1 << 1;
+        const SYMBOLIC = 1 << 2;
+        const SCRIPT = 1 << 3;
+        const NONSYMBOLIC = 1 << 5;
+        const ITALIC = 1 << 6;
+        const ALL_CAP = 1 << 16;
+        const SMALL_CAP = 1 << 17;
+        const FORCE_BOLD = 1 << 18;
+    }
+}
+
 impl FontDescriptor {
+    /// Style flags, useful for picking a substitute when the font is not embedded.
+    pub fn font_flags(&self) -> FontFlags {
+        FontFlags::from_bits_truncate(self.flags)
+    }
+    pub fn is_embedded(&self) -> bool {
+        self.font_file.is_some() || self.font_file2.is_some() || self.font_file3.is_some()
+    }
     pub fn data(&self, resolve: &impl Resolve) -> Option<Result<Arc<[u8]>>> {
         if let Some(ref s) = self.font_file {
             Some(s.data(resolve))
@@ -412,6 +611,17 @@ impl FontDescriptor {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct FontInfo {
+    /// key in the `/Font` resource dictionary, `None` for a font set by an ExtGState
+    pub resource_name: Option<Name>,
+    pub base_font: Option<Name>,
+    pub subtype: FontType,
+    pub embedded: bool,
+    pub cid: bool,
+    pub encoding: Option<BaseEncoding>,
+}
+
 #[derive(Object, Debug, Clone, DataSize)]
 #[pdf(key="Subtype")]
 pub enum FontTypeExt {
@@ -576,10 +786,193 @@ fn parse_cmap(data: &[u8]) -> Result<ToUnicodeMap> {
     Ok(map)
 }
 
+/// Widths of codes 0 to 255 of a standard 14 font, from its AFM metrics.
+///
+/// Codes are mapped to glyph names through `encoding`, or the built-in encoding of the font
+/// (StandardEncoding for the Latin fonts). Also accepts the common Arial and Times New Roman aliases.
+pub fn standard_font_widths(base_font: &str, encoding: Option<&Encoding>) -> Option<Widths> {
+    // subset fonts are prefixed with a tag like `ABCDEF+`
+    let name = match base_font.split_once('+') {
+        Some((tag, name)) if tag.len() == 6 => name,
+        _ => base_font
+    };
+    let font = match name {
+        "Arial" | "ArialMT" => "Helvetica",
+        "Arial,Bold" | "Arial-BoldMT" => "Helvetica-Bold",
+        "Arial,Italic" | "Arial-ItalicMT" => "Helvetica-Oblique",
+        "Arial,BoldItalic" | "Arial-BoldItalicMT" => "Helvetica-BoldOblique",
+        "TimesNewRoman" | "TimesNewRomanPSMT" => "Times-Roman",
+        "TimesNewRoman,Bold" | "TimesNewRomanPS-BoldMT" => "Times-Bold",
+        "TimesNewRoman,Italic" | "TimesNewRomanPS-ItalicMT" => "Times-Italic",
+        "TimesNewRoman,BoldItalic" | "TimesNewRomanPS-BoldItalicMT" => "Times-BoldItalic",
+        "CourierNew" | "CourierNewPSMT" => "Courier",
+        name => name
+    };
+    // only known fonts have a width for `space`
+    metrics::glyph_width(font, "space")?;
+
+    let builtin = |code: u8| match font {
+        "Symbol" | "ZapfDingbats" => metrics::builtin_glyph_name(font, code),
+        _ => BaseEncoding::StandardEncoding.glyph_name(code)
+    };
+    let values = (0 ..= 255u8).map(|code| {
+        let glyph = match encoding {
+            Some(enc) => match (enc.differences.get(&(code as u32)), &enc.base) {
+                (Some(name), _) => Some(name.as_str()),
+                (None, BaseEncoding::None) => builtin(code),
+                (None, base) => base.glyph_name(code)
+            },
+            None => builtin(code)
+        };
+        glyph.and_then(|g| metrics::glyph_width(font, g)).unwrap_or(0) as f32
+    }).collect();
+    Some(Widths { values, default: 0.0, first_char: 0 })
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::font::{utf16be_to_string, utf16be_to_char, utf16be_to_string_lossy};
+
+    #[test]
+    fn test_cid_widths() {
+        use crate::object::{Object, NoResolve};
+        use crate::parser::{parse, ParseFlags};
+        use super::Font;
+
+        let data = b"<< /Type /Font /Subtype /Type0 /BaseFont /Foo /Encoding /Identity-H /DescendantFonts [
+            << /Type /Font /Subtype /CIDFontType2 /BaseFont /Foo
+                /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >>
+                /FontDescriptor << /FontName /Foo /Flags 4 /FontBBox [0 -200 1000 900] /ItalicAngle 0 >>
+                /DW 600 /W [1 [500 550] 10 12 300 20 []] >>
+        ] >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        let cmap = font.cmap().unwrap();
+        assert!(!cmap.vertical);
+        assert_eq!(cmap.cid(0x1234), Some(0x1234));
+
+        let widths = font.widths(&NoResolve).unwrap().unwrap();
+        assert_eq!(widths.get(0), 600.);
+        assert_eq!(widths.get(1), 500.);
+        assert_eq!(widths.get(2), 550.);
+        assert_eq!(widths.get(5), 600.);
+        assert_eq!(widths.get(11), 300.);
+        assert_eq!(widths.get(13), 600.);
+    }
+
+    #[test]
+    fn test_standard_font_widths() {
+        use crate::object::{Object, NoResolve};
+        use crate::parser::{parse, ParseFlags};
+        use super::{Font, standard_font_widths};
+
+        let data = b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        let widths = font.widths(&NoResolve).unwrap().unwrap();
+        assert_eq!(widths.get(b' ' as usize), 278.);
+        assert_eq!(widths.get(b'W' as usize), 944.);
+        assert_eq!(widths.get(b'~' as usize), 584.);
+        // fl in StandardEncoding
+        assert_eq!(widths.get(0xaf), 500.);
+
+        // accented glyphs through WinAnsiEncoding and /Differences
+        let data = b"<< /Type /Font /Subtype /Type1 /BaseFont /Times-Bold
+            /Encoding << /BaseEncoding /WinAnsiEncoding /Differences [200 /Lcaron /lcaron] >> >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        let widths = font.widths(&NoResolve).unwrap().unwrap();
+        assert_eq!(widths.get(0xe9), 444.);
+        assert_eq!(widths.get(0xc9), 394.);
+        assert_eq!(widths.get(0xc8), 667.);
+        assert_eq!(widths.get(0x80), 500.);
+
+        assert_eq!(standard_font_widths("Courier-Bold", None).unwrap().get(0xe1), 600.);
+        assert_eq!(standard_font_widths("ABCDEF+Times-Italic", None).unwrap().get(b'm' as usize), 722.);
+        assert_eq!(standard_font_widths("Times-BoldItalic", None).unwrap().get(b'A' as usize), 667.);
+        assert_eq!(standard_font_widths("Symbol", None).unwrap().get(b'a' as usize), 631.);
+        assert_eq!(standard_font_widths("ZapfDingbats", None).unwrap().get(0x21), 974.);
+        assert!(standard_font_widths("Verdana", None).is_none());
+    }
+
+    #[test]
+    fn test_widths() {
+        use crate::object::{Object, NoResolve};
+        use crate::parser::{parse, ParseFlags};
+        use super::Font;
+
+        let data = b"<< /Type /Font /Subtype /TrueType /BaseFont /Foo /FirstChar 32 /LastChar 34 /Widths [250 333 0] \
+            /FontDescriptor << /FontName /Foo /Flags 32 /FontBBox [0 -200 1000 900] /ItalicAngle 0 /MissingWidth 500 >> >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        let flags = font.info().unwrap().font_descriptor.as_ref().unwrap().font_flags();
+        assert_eq!(flags, super::FontFlags::NONSYMBOLIC);
+
+        let widths = font.widths(&NoResolve).unwrap().unwrap();
+        assert_eq!(widths.get(32), 250.);
+        assert_eq!(widths.get(33), 333.);
+        assert_eq!(widths.get(34), 0.);
+        assert_eq!(widths.get(35), 500.);
+        assert_eq!(widths.get(10), 500.);
+    }
+    #[test]
+    fn test_type3_char_width() {
+        use crate::object::{Object, NoResolve};
+        use crate::parser::{parse, ParseFlags};
+        use crate::content::parse_ops;
+        use super::Type3Font;
+
+        let data = b"<< /FontBBox [0 0 500 500] /FontMatrix [0.002 0 0 0.002 0 0] /CharProcs << >> /Widths [] >>";
+        let font = Type3Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+
+        let ops = parse_ops(b"500 0 0 0 400 450 d1 0 0 400 450 re f", &NoResolve).unwrap();
+        let ((x, y), bbox) = font.char_width(&ops).unwrap();
+        assert_eq!((x, y), (1., 0.));
+        assert_eq!(bbox.unwrap().right, 400.);
+
+        let ops = parse_ops(b"250 0 d0", &NoResolve).unwrap();
+        let (advance, bbox) = font.char_width(&ops).unwrap();
+        assert_eq!(advance, (0.5, 0.));
+        assert!(bbox.is_none());
+        assert!(font.char_width(&[]).is_none());
+    }
+
+    #[test]
+    fn test_vertical_metrics() {
+        use crate::object::{Object, NoResolve};
+        use crate::parser::{parse, ParseFlags};
+        use super::Font;
+
+        let data = b"<< /Type /Font /Subtype /Type0 /BaseFont /Foo /Encoding /Identity-V /DescendantFonts [
+            << /Type /Font /Subtype /CIDFontType0 /BaseFont /Foo
+                /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >>
+                /FontDescriptor << /FontName /Foo /Flags 4 /FontBBox [0 -200 1000 900] /ItalicAngle 0 >>
+                /DW2 [900 -1000] /W2 [1 [-500 250 880 -600 300 880] 10 12 -800 500 900] >>
+        ] >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        assert!(font.is_vertical());
+        let metrics = font.vertical_metrics(&NoResolve).unwrap().unwrap();
+        assert_eq!(metrics.get(1, 1000.), (-500., 250., 880.));
+        assert_eq!(metrics.get(2, 1000.), (-600., 300., 880.));
+        assert_eq!(metrics.get(11, 1000.), (-800., 500., 900.));
+        assert_eq!(metrics.get(5, 600.), (-1000., 300., 900.));
+
+        let data = b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        assert!(font.vertical_metrics(&NoResolve).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_font_info() {
+        use crate::object::{Object, NoResolve};
+        use crate::parser::{parse, ParseFlags};
+        use super::{Font, FontType, BaseEncoding};
+
+        let data = b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>";
+        let font = Font::from_primitive(parse(data, &NoResolve, ParseFlags::ANY).unwrap(), &NoResolve).unwrap();
+        let info = font.font_info(Some("F1".into()));
+        assert!(matches!(info.subtype, FontType::Type1));
+        assert_eq!(info.base_font.as_deref(), Some("Helvetica"));
+        assert_eq!(info.encoding, Some(BaseEncoding::WinAnsiEncoding));
+        assert!(!info.embedded && !info.cid);
+    }
     #[test]
     fn utf16be_to_string_quick() {
         let v = vec![0x20, 0x09];
@@ -17,6 +17,21 @@ pub struct IccInfo {
     #[pdf(key="Metadata")]
     pub metadata: Option<Stream<()>>,
 }
+impl IccInfo {
+    /// The space to use instead of the ICC profile: `/Alternate` if given,
+    /// otherwise the device space with `/N` components.
+    pub fn alternate_space(&self) -> Option<ColorSpace> {
+        if let Some(ref alt) = self.alternate {
+            return Some((**alt).clone());
+        }
+        match self.components {
+            1 => Some(ColorSpace::DeviceGray),
+            3 => Some(ColorSpace::DeviceRGB),
+            4 => Some(ColorSpace::DeviceCMYK),
+            _ => None
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum ColorSpace {
@@ -24,9 +39,10 @@ pub enum ColorSpace {
     DeviceRGB,
     DeviceCMYK,
     DeviceN { names: Vec<Name>, alt: Box<ColorSpace>, tint: Function, attr: Option<Dictionary> },
-    CalGray(Dictionary),
-    CalRGB(Dictionary),
+    CalGray(CalGray),
+    CalRGB(CalRgb),
     CalCMYK(Dictionary),
+    Lab(Lab),
     Indexed(Box<ColorSpace>, Arc<[u8]>),
     Separation(Name, Box<ColorSpace>, Function),
     Icc(RcRef<Stream<IccInfo>>),
@@ -48,9 +64,10 @@ impl DataSize for ColorSpace {
                 tint.estimate_heap_size() +
                 attr.estimate_heap_size()
             }
-            ColorSpace::CalGray(ref d) | ColorSpace::CalRGB(ref d) | ColorSpace::CalCMYK(ref d) => {
-                d.estimate_heap_size()
-            }
+            ColorSpace::CalCMYK(ref d) => d.estimate_heap_size(),
+            ColorSpace::CalGray(ref c) => c.estimate_heap_size(),
+            ColorSpace::CalRGB(ref c) => c.estimate_heap_size(),
+            ColorSpace::Lab(ref c) => c.estimate_heap_size(),
             ColorSpace::Indexed(ref cs, ref data) => {
                 cs.estimate_heap_size() + data.estimate_heap_size()
             }
@@ -65,6 +82,133 @@ impl DataSize for ColorSpace {
     }
 }
 
+/// Parameters of a `CalGray` color space.
+#[derive(Object, Debug, Clone, DataSize)]
+pub struct CalGray {
+    #[pdf(key="WhitePoint")]
+    pub white_point: Vec<f32>,
+
+    #[pdf(key="BlackPoint", default="vec![0., 0., 0.]")]
+    pub black_point: Vec<f32>,
+
+    #[pdf(key="Gamma", default="1.")]
+    pub gamma: f32,
+}
+impl CalGray {
+    /// Convert the `A` component (in `0 ..= 1`) to an sRGB encoded gray level.
+    pub fn to_gray(&self, a: f32) -> f32 {
+        srgb_gamma(a.clamp(0., 1.).powf(self.gamma))
+    }
+}
+
+/// Parameters of a `CalRGB` color space.
+#[derive(Object, Debug, Clone, DataSize)]
+pub struct CalRgb {
+    #[pdf(key="WhitePoint")]
+    pub white_point: Vec<f32>,
+
+    #[pdf(key="BlackPoint", default="vec![0., 0., 0.]")]
+    pub black_point: Vec<f32>,
+
+    #[pdf(key="Gamma", default="vec![1., 1., 1.]")]
+    pub gamma: Vec<f32>,
+
+    /// `[X_A Y_A Z_A X_B Y_B Z_B X_C Y_C Z_C]`
+    #[pdf(key="Matrix", default="vec![1., 0., 0., 0., 1., 0., 0., 0., 1.]")]
+    pub matrix: Vec<f32>,
+}
+impl CalRgb {
+    /// Convert `A B C` components (each in `0 ..= 1`) to sRGB.
+    pub fn to_rgb(&self, abc: [f32; 3]) -> [f32; 3] {
+        let mut xyz = [0.; 3];
+        for (i, &c) in abc.iter().enumerate() {
+            let g = self.gamma.get(i).copied().unwrap_or(1.);
+            let c = c.clamp(0., 1.).powf(g);
+            for (j, v) in xyz.iter_mut().enumerate() {
+                *v += self.matrix.get(3 * i + j).copied().unwrap_or(0.) * c;
+            }
+        }
+        xyz_to_rgb(xyz, &self.white_point)
+    }
+}
+
+/// Parameters of a `Lab` color space.
+#[derive(Object, Debug, Clone, DataSize)]
+pub struct Lab {
+    #[pdf(key="WhitePoint")]
+    pub white_point: Vec<f32>,
+
+    #[pdf(key="BlackPoint", default="vec![0., 0., 0.]")]
+    pub black_point: Vec<f32>,
+
+    /// `[a_min a_max b_min b_max]`
+    #[pdf(key="Range", default="vec![-100., 100., -100., 100.]")]
+    pub range: Vec<f32>,
+}
+impl Lab {
+    fn range(&self, i: usize) -> (f32, f32) {
+        match self.range.get(2 * i .. 2 * i + 2) {
+            Some(&[min, max]) => (min, max),
+            _ => (-100., 100.)
+        }
+    }
+    /// Convert `L* a* b*` to sRGB. `a*` and `b*` are clamped to `/Range`.
+    pub fn to_rgb(&self, lab: [f32; 3]) -> [f32; 3] {
+        let (a_min, a_max) = self.range(0);
+        let (b_min, b_max) = self.range(1);
+        let l = lab[0].clamp(0., 100.);
+        let a = lab[1].clamp(a_min, a_max);
+        let b = lab[2].clamp(b_min, b_max);
+
+        let m = (l + 16.) / 116.;
+        let g = |x: f32| if x >= 6. / 29. { x * x * x } else { 108. / 841. * (x - 4. / 29.) };
+        let w = |i: usize| self.white_point.get(i).copied().unwrap_or(1.);
+        let xyz = [
+            w(0) * g(m + a / 500.),
+            w(1) * g(m),
+            w(2) * g(m - b / 200.),
+        ];
+        xyz_to_rgb(xyz, &self.white_point)
+    }
+    /// Components of an `Indexed` lookup table entry, which are scaled to `0 ..= 100` for `L*` and `/Range` for `a*` and `b*`.
+    pub fn from_bytes(&self, bytes: [u8; 3]) -> [f32; 3] {
+        let scale = |v: u8, (min, max): (f32, f32)| min + v as f32 / 255. * (max - min);
+        [
+            scale(bytes[0], (0., 100.)),
+            scale(bytes[1], self.range(0)),
+            scale(bytes[2], self.range(1)),
+        ]
+    }
+}
+
+/// Convert CIE XYZ relative to `white_point` into sRGB, each component in `0 ..= 1`.
+///
+/// The white point is mapped onto D65 by scaling each axis, which is good enough for display.
+pub fn xyz_to_rgb(xyz: [f32; 3], white_point: &[f32]) -> [f32; 3] {
+    const D65: [f32; 3] = [0.9505, 1.0, 1.089];
+    let mut adapted = xyz;
+    for (i, v) in adapted.iter_mut().enumerate() {
+        if let Some(&w) = white_point.get(i).filter(|&&w| w > 0.) {
+            *v *= D65[i] / w;
+        }
+    }
+    let [x, y, z] = adapted;
+    let linear = [
+         3.2406 * x - 1.5372 * y - 0.4986 * z,
+        -0.9689 * x + 1.8758 * y + 0.0415 * z,
+         0.0557 * x - 0.2040 * y + 1.0570 * z,
+    ];
+    linear.map(|c| srgb_gamma(c.clamp(0., 1.)))
+}
+
+fn srgb_gamma(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1. / 2.4) - 0.055
+    }
+}
+
 fn get_index(arr: &[Primitive], idx: usize) -> Result<&Primitive> {
      arr.get(idx).ok_or(PdfError::Bounds { index: idx, len: arr.len() })
 }
@@ -75,17 +219,82 @@ impl Object for ColorSpace {
     }
 }
 impl ColorSpace {
+    /// Number of color components, or `None` for `Pattern` and unresolved spaces.
+    pub fn components(&self) -> Option<usize> {
+        match *self {
+            ColorSpace::DeviceGray | ColorSpace::CalGray(_) => Some(1),
+            ColorSpace::DeviceRGB | ColorSpace::CalRGB(_) | ColorSpace::Lab(_) => Some(3),
+            ColorSpace::DeviceCMYK | ColorSpace::CalCMYK(_) => Some(4),
+            ColorSpace::DeviceN { ref names, .. } => Some(names.len()),
+            ColorSpace::Indexed(..) | ColorSpace::Separation(..) => Some(1),
+            ColorSpace::Icc(ref s) => Some(s.info.components as usize),
+            ColorSpace::Pattern | ColorSpace::Named(_) | ColorSpace::Other(_) => None,
+        }
+    }
+    /// For `Indexed`: the palette entry for `index`, in the components of the base color space.
+    ///
+    /// Indices past the end of the palette are clamped to the last entry.
+    pub fn index_color(&self, index: usize) -> Option<&[u8]> {
+        match *self {
+            ColorSpace::Indexed(ref base, ref lookup) => {
+                let n = base.components().filter(|&n| n > 0)?;
+                let entries = lookup.len() / n;
+                if entries == 0 {
+                    return None;
+                }
+                if index >= entries {
+                    warn!("color index {} out of range (hival = {})", index, entries - 1);
+                }
+                let i = index.min(entries - 1);
+                Some(&lookup[n * i .. n * (i + 1)])
+            }
+            _ => None
+        }
+    }
+    /// `Separation /None`, or a `DeviceN` of only `None` colorants: painting produces no marks.
+    pub fn is_none_colorant(&self) -> bool {
+        match *self {
+            ColorSpace::Separation(ref name, ..) => name == "None",
+            ColorSpace::DeviceN { ref names, .. } => !names.is_empty() && names.iter().all(|n| n == "None"),
+            _ => false
+        }
+    }
+    /// `Separation /All`: the colorant is applied to every plate, as for registration marks.
+    pub fn is_all_colorant(&self) -> bool {
+        matches!(*self, ColorSpace::Separation(ref name, ..) if name == "All")
+    }
+    /// For `Separation` and `DeviceN`: run the tint transform on `tint`,
+    /// returning the alternate color space and the resulting components in it.
+    pub fn to_alternate(&self, tint: &[f32]) -> Result<Option<(&ColorSpace, Vec<f32>)>> {
+        let (alt, f) = match *self {
+            ColorSpace::Separation(_, ref alt, ref f) => (&**alt, f),
+            ColorSpace::DeviceN { ref alt, ref tint, .. } => (&**alt, tint),
+            _ => return Ok(None)
+        };
+        let n = match alt.components() {
+            Some(n) => n,
+            None => bail!("invalid alternate color space {:?}", alt)
+        };
+        let mut out = vec![0.; n];
+        f.apply(tint, &mut out)?;
+        Ok(Some((alt, out)))
+    }
+    /// Color spaces that can be referred to by name without a resource entry.
+    ///
+    /// Also accepts the abbreviations used in inline images.
+    pub fn device(name: &str) -> Option<ColorSpace> {
+        match name {
+            "DeviceGray" | "G" => Some(ColorSpace::DeviceGray),
+            "DeviceRGB" | "RGB" => Some(ColorSpace::DeviceRGB),
+            "DeviceCMYK" | "CMYK" => Some(ColorSpace::DeviceCMYK),
+            "Pattern" => Some(ColorSpace::Pattern),
+            _ => None
+        }
+    }
     fn from_primitive_depth(p: Primitive, resolve: &impl Resolve, depth: usize) -> Result<ColorSpace> {
         let p = p.resolve(resolve)?;
         if let Ok(name) = p.as_name() {
-            let cs = match name {
-                "DeviceGray" => ColorSpace::DeviceGray,
-                "DeviceRGB" => ColorSpace::DeviceRGB,
-                "DeviceCMYK" => ColorSpace::DeviceCMYK,
-                "Pattern" => ColorSpace::Pattern,
-                name => ColorSpace::Named(name.into()),
-            };
-            return Ok(cs);
+            return Ok(ColorSpace::device(name).unwrap_or_else(|| ColorSpace::Named(name.into())));
         }
         let arr = t!(p.into_array());
         let typ = t!(t!(get_index(&arr, 0)).as_name());
@@ -94,8 +303,13 @@ impl ColorSpace {
             bail!("ColorSpace base recursion");
         }
         match typ {
-            "Indexed" => {
+            "Indexed" | "I" => {
                 let base = Box::new(t!(ColorSpace::from_primitive_depth(t!(get_index(&arr, 1)).clone(), resolve, depth-1)));
+                let mut hival = t!(t!(get_index(&arr, 2)).as_integer());
+                if !(0 ..= 255).contains(&hival) {
+                    warn!("Indexed hival {} is out of range", hival);
+                    hival = hival.clamp(0, 255);
+                }
                 let lookup = match t!(get_index(&arr, 3)) {
                     &Primitive::Reference(r) => resolve.resolve(r)?,
                     p => p.clone()
@@ -114,6 +328,11 @@ impl ColorSpace {
                         found: p.get_debug_name()
                     })
                 };
+                // drop trailing data beyond hival
+                let lookup = match base.components() {
+                    Some(n) if lookup.len() > (hival as usize + 1) * n => lookup[.. (hival as usize + 1) * n].into(),
+                    _ => lookup
+                };
                 Ok(ColorSpace::Indexed(base, lookup))
             }
             "Separation" => {
@@ -135,17 +354,21 @@ impl ColorSpace {
                 Ok(ColorSpace::DeviceN { names, alt, tint, attr})
             }
             "CalGray" => {
-                let dict = Dictionary::from_primitive(t!(get_index(&arr, 1)).clone(), resolve)?;
-                Ok(ColorSpace::CalGray(dict))
+                let cal = t!(CalGray::from_primitive(t!(get_index(&arr, 1)).clone(), resolve));
+                Ok(ColorSpace::CalGray(cal))
             }
             "CalRGB" => {
-                let dict = Dictionary::from_primitive(t!(get_index(&arr, 1)).clone(), resolve)?;
-                Ok(ColorSpace::CalRGB(dict))
+                let cal = t!(CalRgb::from_primitive(t!(get_index(&arr, 1)).clone(), resolve));
+                Ok(ColorSpace::CalRGB(cal))
             }
             "CalCMYK" => {
                 let dict = Dictionary::from_primitive(t!(get_index(&arr, 1)).clone(), resolve)?;
                 Ok(ColorSpace::CalCMYK(dict))
             }
+            "Lab" => {
+                let lab = t!(Lab::from_primitive(t!(get_index(&arr, 1)).clone(), resolve));
+                Ok(ColorSpace::Lab(lab))
+            }
             "Pattern" => {
                 Ok(ColorSpace::Pattern)
             }
@@ -154,11 +377,140 @@ impl ColorSpace {
     }
 }
 impl ObjectWrite for ColorSpace {
-    fn to_primitive(&self, _update: &mut impl Updater) -> Result<Primitive> {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
         match *self {
+            ColorSpace::DeviceGray => Ok(Primitive::name("DeviceGray")),
             ColorSpace::DeviceCMYK => Ok(Primitive::name("DeviceCMYK")),
             ColorSpace::DeviceRGB => Ok(Primitive::name("DeviceRGB")),
+            ColorSpace::Pattern => Ok(Primitive::name("Pattern")),
+            ColorSpace::Named(ref name) => name.to_primitive(update),
+            ColorSpace::Icc(ref stream) => Ok(Primitive::Array(vec![Primitive::name("ICCBased"), stream.to_primitive(update)?])),
+            ColorSpace::Other(ref arr) => Ok(Primitive::Array(arr.clone())),
             _ => unimplemented!()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+        a.iter().zip(&b).all(|(x, y)| (x - y).abs() < 0.01)
+    }
+
+    #[test]
+    fn test_device_n() {
+        let data = b"[/DeviceN [/Orange /Green] /DeviceCMYK \
+            << /FunctionType 2 /Domain [0 1 0 1] /C0 [0 0 0 0] /C1 [0 0.5 1 0] /N 1 >>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let cs = ColorSpace::from_primitive(p, &NoResolve).unwrap();
+        assert_eq!(cs.components(), Some(2));
+        let (alt, out) = cs.to_alternate(&[0.5, 0.2]).unwrap().unwrap();
+        assert!(matches!(alt, ColorSpace::DeviceCMYK));
+        assert_eq!(out, [0., 0.25, 0.5, 0.]);
+        assert!(ColorSpace::DeviceRGB.to_alternate(&[0.]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_indexed() {
+        let data = b"[/Indexed /DeviceRGB 1 <FF0000 00FF00 0000FF>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let cs = ColorSpace::from_primitive(p, &NoResolve).unwrap();
+        assert_eq!(cs.index_color(0), Some(&[255, 0, 0][..]));
+        assert_eq!(cs.index_color(1), Some(&[0, 255, 0][..]));
+        assert_eq!(cs.index_color(7), Some(&[0, 255, 0][..]));
+        assert_eq!(ColorSpace::DeviceRGB.index_color(0), None);
+
+        // abbreviated form from an inline image, over a gray base
+        let data = b"[/I /G 2 <00 80 FF>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let cs = ColorSpace::from_primitive(p, &NoResolve).unwrap();
+        assert!(matches!(cs, ColorSpace::Indexed(ref base, _) if matches!(**base, ColorSpace::DeviceGray)));
+        assert_eq!(cs.index_color(1), Some(&[0x80][..]));
+
+        // hival is clamped to 255
+        let data = b"[/Indexed /DeviceGray 300 <00 FF>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let cs = ColorSpace::from_primitive(p, &NoResolve).unwrap();
+        assert_eq!(cs.index_color(1), Some(&[0xFF][..]));
+
+        // a base without components has no palette entries
+        let cs = ColorSpace::Indexed(Box::new(ColorSpace::DeviceN {
+            names: vec![],
+            alt: Box::new(ColorSpace::DeviceGray),
+            tint: Function::Calculator,
+            attr: None,
+        }), vec![0; 3].into());
+        assert_eq!(cs.index_color(0), None);
+    }
+
+    #[test]
+    fn test_special_colorants() {
+        let parse = |data: &[u8]| {
+            let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+            ColorSpace::from_primitive(p, &NoResolve).unwrap()
+        };
+        let f = "<< /FunctionType 2 /Domain [0 1] /C0 [0] /C1 [1] /N 1 >>";
+        let none = parse(format!("[/Separation /None /DeviceGray {}]", f).as_bytes());
+        assert!(none.is_none_colorant() && !none.is_all_colorant());
+        let all = parse(format!("[/Separation /All /DeviceGray {}]", f).as_bytes());
+        assert!(all.is_all_colorant() && !all.is_none_colorant());
+        let n = parse(format!("[/DeviceN [/None /None] /DeviceGray {}]", f).as_bytes());
+        assert!(n.is_none_colorant());
+        let spot = parse(format!("[/Separation /PANTONE#20123 /DeviceGray {}]", f).as_bytes());
+        assert!(!spot.is_none_colorant() && !spot.is_all_colorant());
+    }
+
+    #[test]
+    fn test_icc_alternate() {
+        let info = |components, alternate| IccInfo { components, alternate, range: None, metadata: None };
+        assert!(matches!(info(1, None).alternate_space(), Some(ColorSpace::DeviceGray)));
+        assert!(matches!(info(3, None).alternate_space(), Some(ColorSpace::DeviceRGB)));
+        assert!(matches!(info(4, None).alternate_space(), Some(ColorSpace::DeviceCMYK)));
+        assert!(info(2, None).alternate_space().is_none());
+        let lab = info(3, Some(Box::new(ColorSpace::Named("Lab0".into()))));
+        assert!(matches!(lab.alternate_space(), Some(ColorSpace::Named(_))));
+    }
+
+    #[test]
+    fn test_lab() {
+        let data = b"[/Lab << /WhitePoint [0.9505 1 1.089] /Range [-128 127 -128 127] >>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let lab = match ColorSpace::from_primitive(p, &NoResolve).unwrap() {
+            ColorSpace::Lab(lab) => lab,
+            cs => panic!("expected Lab, got {:?}", cs)
+        };
+        assert!(close(lab.to_rgb([100., 0., 0.]), [1., 1., 1.]));
+        assert!(close(lab.to_rgb([0., 0., 0.]), [0., 0., 0.]));
+        assert!(close(lab.to_rgb([53.24, 80.09, 67.2]), [1., 0., 0.]));
+        assert!(close(lab.from_bytes([255, 0, 255]), [100., -128., 127.]));
+    }
+
+    #[test]
+    fn test_cal_gray() {
+        let data = b"[/CalGray << /WhitePoint [0.9505 1 1.089] /Gamma 2.2 >>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let cal = match ColorSpace::from_primitive(p, &NoResolve).unwrap() {
+            ColorSpace::CalGray(cal) => cal,
+            cs => panic!("expected CalGray, got {:?}", cs)
+        };
+        assert_eq!(cal.to_gray(0.), 0.);
+        assert!((cal.to_gray(1.) - 1.).abs() < 0.001);
+        assert!((cal.to_gray(0.5) - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_cal_rgb() {
+        let data = b"[/CalRGB << /WhitePoint [0.9505 1 1.089] /Gamma [2.2 2.2 2.2] \
+            /Matrix [0.4124 0.2126 0.0193 0.3576 0.7152 0.1192 0.1805 0.0722 0.9505] >>]";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let cal = match ColorSpace::from_primitive(p, &NoResolve).unwrap() {
+            ColorSpace::CalRGB(cal) => cal,
+            cs => panic!("expected CalRGB, got {:?}", cs)
+        };
+        assert!(close(cal.to_rgb([1., 1., 1.]), [1., 1., 1.]));
+        assert!(close(cal.to_rgb([0., 0., 0.]), [0., 0., 0.]));
+        assert!(close(cal.to_rgb([1., 0., 0.]), [1., 0., 0.]));
+    }
+}
@@ -3,6 +3,7 @@ use crate::object::*;
 use crate::error::*;
 use itertools::izip;
 use datasize::DataSize;
+use std::convert::TryFrom;
 
 #[derive(Object, Debug, Clone)]
 struct RawFunction {
@@ -19,7 +20,7 @@ struct RawFunction {
     size: Option<Vec<u32>>,
 
     #[pdf(key="BitsPerSample")]
-    _bits_per_sample: Option<u32>,
+    bits_per_sample: Option<u32>,
 
     #[pdf(key="Order", default="1")]
     order: u32,
@@ -46,11 +47,20 @@ struct Function2 {
     exponent: f32,
 }
 
+#[derive(Object, Debug, Clone)]
+struct Function3 {
+    #[pdf(key="Functions")]
+    functions: Vec<Function>,
+
+    #[pdf(key="Bounds")]
+    bounds: Vec<f32>,
+}
+
 #[derive(Debug, Clone, DataSize)]
 pub enum Function {
     Sampled(SampledFunction),
     Interpolated(Vec<InterpolatedFunctionDim>),
-    Stiching,
+    Stiching(StichingFunction),
     Calculator,
     PostScript { func: PsFunc, domain: Vec<f32>, range: Vec<f32> },
 }
@@ -69,6 +79,7 @@ impl Function {
                 }
                 Ok(())
             }
+            Function::Stiching(ref func) => func.apply(x, out),
             Function::PostScript { ref func, .. } => func.exec(x, out),
             _ => bail!("unimplemted function {:?}", self)
         }
@@ -77,6 +88,7 @@ impl Function {
         match *self {
             Function::PostScript { ref domain, .. } => domain.len() / 2,
             Function::Sampled(ref f) => f.input.len(),
+            Function::Interpolated(_) | Function::Stiching(_) => 1,
             _ => panic!()
         }
     }
@@ -84,6 +96,8 @@ impl Function {
         match *self {
             Function::PostScript { ref range, .. } => range.len() / 2,
             Function::Sampled(ref f) => f.output.len(),
+            Function::Interpolated(ref parts) => parts.len(),
+            Function::Stiching(ref f) => f.functions[0].output_dim(),
             _ => panic!()
         }
     }
@@ -92,6 +106,9 @@ impl FromDict for Function {
     fn from_dict(dict: Dictionary, resolve: &impl Resolve) -> Result<Self> {
         use std::f32::INFINITY;
         let raw = RawFunction::from_dict(dict, resolve)?;
+        if raw.domain.len() < 2 {
+            bail!("function Domain needs at least two values, found {:?}", raw.domain);
+        }
         match raw.function_type {
             2 => {
                 let f2 = Function2::from_dict(raw.other, resolve)?;
@@ -118,6 +135,20 @@ impl FromDict for Function {
                 }
                 Ok(Function::Interpolated(parts))
             },
+            3 => {
+                let f3 = Function3::from_dict(raw.other, resolve)?;
+                let encode = try_opt!(raw.encode);
+                let k = f3.functions.len();
+                if k == 0 || f3.bounds.len() + 1 != k || encode.len() != 2 * k {
+                    bail!("inconsistent stitching function: {} functions, {} bounds, {} encode values", k, f3.bounds.len(), encode.len())
+                }
+                Ok(Function::Stiching(StichingFunction {
+                    domain: (raw.domain[0], raw.domain[1]),
+                    functions: f3.functions,
+                    bounds: f3.bounds,
+                    encode,
+                }))
+            }
             i => {
                 dbg!(raw);
                 bail!("unsupported function type {}", i)
@@ -137,7 +168,7 @@ impl Object for Function {
                         let s = std::str::from_utf8(&data)?;
                         let func = PsFunc::parse(s)?;
                         let info = stream.info.info;
-                        Ok(Function::PostScript { func, domain: info.domain, range: info.range.unwrap() })
+                        Ok(Function::PostScript { func, domain: info.domain, range: try_opt!(info.range) })
                     },
                     0 => {
                         let info = stream.info.info;
@@ -149,23 +180,38 @@ impl Object for Function {
 
                         let size = try_opt!(info.size);
                         let range = try_opt!(info.range);
+                        let bits = try_opt!(info.bits_per_sample);
+                        if !matches!(bits, 1 | 2 | 4 | 8 | 12 | 16 | 24 | 32) {
+                            bail!("invalid BitsPerSample {}", bits);
+                        }
+                        if size.len() * 2 != info.domain.len() || size.contains(&0) {
+                            bail!("Size {:?} does not match Domain {:?}", size, info.domain);
+                        }
                         let encode = info.encode.unwrap_or_else(|| size.iter().flat_map(|&n| [0.0, (n-1) as f32]).collect());
                         let decode = info.decode.unwrap_or_else(|| range.clone());
+                        let n_samples = size.iter().try_fold(range.len() / 2, |n, &s| n.checked_mul(s as usize));
+                        match n_samples {
+                            Some(n) if n.saturating_mul(bits as usize).div_ceil(8) <= data.len() => {}
+                            _ => bail!("not enough sample data for Size {:?}", size)
+                        }
+                        let max_sample = ((1u64 << bits) - 1) as f32;
 
                         Ok(Function::Sampled(SampledFunction {
-                            input: izip!(info.domain.chunks_exact(2), encode.chunks_exact(2), size.iter()).map(|(c, e, &s)| {
+                            input: izip!(info.domain.chunks_exact(2), encode.chunks_exact(2), size.iter()).map(|(d, e, &s)| {
+                                let encode_scale = if d[1] > d[0] { (e[1] - e[0]) / (d[1] - d[0]) } else { 0.0 };
                                 SampledFunctionInput {
-                                    domain: (c[0], c[1]),
-                                    encode_offset: e[0],
-                                    encode_scale: e[1],
+                                    domain: (d[0], d[1]),
+                                    encode_offset: e[0] - d[0] * encode_scale,
+                                    encode_scale,
                                     size: s as usize,
                                 }
                             }).collect(),
                             output: decode.chunks_exact(2).map(|c| SampledFunctionOutput { 
                                 offset: c[0],
-                                scale: (c[1] - c[0]) / 255.,
+                                scale: (c[1] - c[0]) / max_sample,
                             }).collect(),
                             data,
+                            bits_per_sample: bits as u8,
                             order,
                             range,
                         }))
@@ -178,6 +224,35 @@ impl Object for Function {
         }
     }
 }
+/// Only functions given by a dictionary (types 2 and 3) can be written.
+impl ObjectWrite for Function {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
+        let mut dict = Dictionary::new();
+        match *self {
+            Function::Interpolated(ref parts) => {
+                let first = try_opt!(parts.first());
+                dict.insert("FunctionType", Primitive::Integer(2));
+                dict.insert("Domain", vec![first.input_range.0, first.input_range.1].to_primitive(update)?);
+                if parts.iter().all(|p| p.output_range.0.is_finite() && p.output_range.1.is_finite()) {
+                    let range: Vec<f32> = parts.iter().flat_map(|p| [p.output_range.0, p.output_range.1]).collect();
+                    dict.insert("Range", range.to_primitive(update)?);
+                }
+                dict.insert("C0", parts.iter().map(|p| p.c0).collect::<Vec<f32>>().to_primitive(update)?);
+                dict.insert("C1", parts.iter().map(|p| p.c1).collect::<Vec<f32>>().to_primitive(update)?);
+                dict.insert("N", Primitive::Number(first.exponent));
+            }
+            Function::Stiching(ref f) => {
+                dict.insert("FunctionType", Primitive::Integer(3));
+                dict.insert("Domain", vec![f.domain.0, f.domain.1].to_primitive(update)?);
+                dict.insert("Functions", f.functions.to_primitive(update)?);
+                dict.insert("Bounds", f.bounds.to_primitive(update)?);
+                dict.insert("Encode", f.encode.to_primitive(update)?);
+            }
+            _ => bail!("writing sampled and PostScript functions is not supported")
+        }
+        Ok(Primitive::Dictionary(dict))
+    }
+}
 
 
 #[derive(Debug, Clone, DataSize)]
@@ -188,10 +263,16 @@ struct SampledFunctionInput {
     size: usize,
 }
 impl SampledFunctionInput {
-    fn map(&self, x: f32) -> (usize, usize, f32) {
+    /// Returns the sample index below `x` and the fraction towards the next one.
+    fn map(&self, x: f32) -> (usize, f32) {
         let x = x.clamp(self.domain.0, self.domain.1);
-        let y = x.mul_add(self.encode_scale, self.encode_offset);
-        (y.floor() as usize, self.size, y.fract())
+        let last = (self.size - 1) as f32;
+        let y = x.mul_add(self.encode_scale, self.encode_offset).clamp(0., last);
+        if y >= last {
+            (self.size - 1, 0.)
+        } else {
+            (y as usize, y.fract())
+        }
     }
 }
 
@@ -213,15 +294,28 @@ enum Interpolation {
     Cubic,
 }
 
+/// Type 0 function: a table of samples, evaluated with multilinear interpolation.
+///
+/// Cubic spline interpolation (`/Order 3`) is approximated with linear interpolation.
 #[derive(Debug, Clone, DataSize)]
 pub struct SampledFunction {
     input: Vec<SampledFunctionInput>,
     output: Vec<SampledFunctionOutput>,
     data: Arc<[u8]>,
+    bits_per_sample: u8,
     order: Interpolation,
     range: Vec<f32>,
 }
 impl SampledFunction {
+    /// Read the `n`th sample of the table (samples are packed MSB first).
+    fn sample(&self, n: usize) -> Result<u32> {
+        let bits = self.bits_per_sample as usize;
+        let start = n * bits;
+        let bytes = try_opt!(self.data.get(start / 8 .. (start + bits).div_ceil(8)));
+        let mut v = bytes.iter().fold(0u64, |v, &b| v << 8 | b as u64);
+        v >>= (8 - (start + bits) % 8) % 8;
+        Ok((v & ((1u64 << bits) - 1)) as u32)
+    }
     fn apply(&self, x: &[f32], out: &mut [f32]) -> Result<()> {
         if x.len() != self.input.len() {
             bail!("input dimension mismatch {} != {}", x.len(), self.input.len());
@@ -230,89 +324,72 @@ impl SampledFunction {
         if out.len() * 2 != self.range.len() {
             bail!("output dimension mismatch 2 * {} != {}", out.len(), self.range.len())
         }
-        match x.len() {
-            1 => {
-                match self.order {
-                    Interpolation::Linear => {
-                        let (i, _, s) = self.input[0].map(x[0]);
-                        let idx = i * n_out;
-
-                        for (o, &a) in out.iter_mut().zip(&self.data[idx..]) {
-                            *o = a as f32 * (1. - s);
-                        }
-                        for (o, &b) in out.iter_mut().zip(&self.data[idx + n_out..]) {
-                            *o += b as f32 * s;
-                        }
-                    }
-                    _ => unimplemented!()
-                }
-            }
-            2 => match self.order {
-                Interpolation::Linear => {
-                    let (i0, s0, f0) = self.input[0].map(x[0]);
-                    let (i1,  _, f1) = self.input[1].map(x[1]);
-                    let (j0, j1) = (i0+1, i1+1);
-                    let (g0, g1) = (1. - f0, 1. - f1);
-                    
-                    out.fill(0.0);
-                    let mut add = |i0, i1, f| {
-                        let idx = (i0 + s0 * i1) * n_out;
-                        
-                        if let Some(part) = self.data.get(idx .. idx+n_out) {
-                            for (o, &b) in out.iter_mut().zip(part) {
-                                *o += f * b as f32;
-                            }
-                        }
-                    };
+        if x.len() > 16 {
+            bail!("too many inputs: {}", x.len());
+        }
+        // (index, fraction, stride) for each input dimension
+        let mut stride = 1;
+        let pos: Vec<_> = self.input.iter().zip(x).map(|(input, &x)| {
+            let (i, f) = input.map(x);
+            let s = stride;
+            stride *= input.size;
+            (i, f, s)
+        }).collect();
 
-                    add(i0, i1, g0 * g1);
-                    add(j0, i1, f0 * g1);
-                    add(i0, j1, g0 * f1);
-                    add(j0, j1, f0 * f1);
+        out.fill(0.0);
+        // sum over the corners of the surrounding hypercube
+        for corner in 0 .. 1usize << pos.len() {
+            let mut weight = 1.0;
+            let mut idx = 0;
+            for (d, &(i, f, s)) in pos.iter().enumerate() {
+                if corner & (1 << d) != 0 {
+                    weight *= f;
+                    idx += (i + 1) * s;
+                } else {
+                    weight *= 1. - f;
+                    idx += i * s;
                 }
-                _ => unimplemented!()
             }
-            3 => match self.order {
-                Interpolation::Linear => {
-                    let (i0, s0, f0) = self.input[0].map(x[0]);
-                    let (i1, s1, f1) = self.input[1].map(x[1]);
-                    let (i2,  _, f2) = self.input[2].map(x[2]);
-                    let (j0, j1, j2) = (i0+1, i1+1, i2+1);
-                    let (g0, g1, g2) = (1. - f0, 1. - f1, 1. - f2);
-                    
-                    out.fill(0.0);
-                    let mut add = |i0, i1, i2, f| {
-                        let idx = (i0 + s0 * (i1 + s1 * i2)) * n_out;
-                        
-                        if let Some(part) = self.data.get(idx .. idx+n_out) {
-                            for (o, &b) in out.iter_mut().zip(part) {
-                                *o += f * b as f32;
-                            }
-                        }
-                    };
-
-                    add(i0, i1, i2, g0 * g1 * g2);
-                    add(j0, i1, i2, f0 * g1 * g2);
-                    add(i0, j1, i2, g0 * f1 * g2);
-                    add(j0, j1, i2, f0 * f1 * g2);
-
-                    add(i0, i1, j2, g0 * g1 * f2);
-                    add(j0, i1, j2, f0 * g1 * f2);
-                    add(i0, j1, j2, g0 * f1 * f2);
-                    add(j0, j1, j2, f0 * f1 * f2);
-                }
-                _ => unimplemented!()
+            if weight == 0.0 {
+                continue;
+            }
+            for (k, o) in out.iter_mut().enumerate() {
+                *o += weight * self.sample(idx * n_out + k)? as f32;
             }
-            n => bail!("Order {}", n)
         }
-        for (o, y) in self.output.iter().zip(out.iter_mut()) {
-            *y = o.map(*y);
+        for ((o, y), r) in self.output.iter().zip(out.iter_mut()).zip(self.range.chunks_exact(2)) {
+            *y = o.map(*y).clamp(r[0], r[1]);
         }
         Ok(())
     }
 }
 
 
+/// Type 3 function: combines several 1-in functions, each covering a subdomain split at `bounds`.
+#[derive(Debug, Clone, DataSize)]
+pub struct StichingFunction {
+    pub domain: (f32, f32),
+    pub functions: Vec<Function>,
+    pub bounds: Vec<f32>,
+    pub encode: Vec<f32>,
+}
+impl StichingFunction {
+    pub fn apply(&self, x: &[f32], out: &mut [f32]) -> Result<()> {
+        let (d0, d1) = self.domain;
+        let x = try_opt!(x.first()).max(d0).min(d1);
+        let i = self.bounds.iter().position(|&b| x < b).unwrap_or(self.bounds.len());
+        let low = if i == 0 { d0 } else { self.bounds[i - 1] };
+        let high = self.bounds.get(i).cloned().unwrap_or(d1);
+        let (e0, e1) = (self.encode[2 * i], self.encode[2 * i + 1]);
+        let t = if high > low {
+            e0 + (x - low) * (e1 - e0) / (high - low)
+        } else {
+            e0
+        };
+        self.functions[i].apply(&[t], out)
+    }
+}
+
 #[derive(Debug, Clone, DataSize)]
 pub struct InterpolatedFunctionDim {
     pub input_range: (f32, f32),
@@ -332,93 +409,361 @@ impl InterpolatedFunctionDim {
 #[derive(Debug)]
 pub enum PostScriptError {
     StackUnderflow,
-    IncorrectStackSize
+    StackOverflow,
+    IncorrectStackSize,
+    TypeCheck,
 }
+
+/// A value on the operand stack of a calculator function.
+#[derive(Debug, Copy, Clone)]
+enum PsValue {
+    Bool(bool),
+    Number(f32),
+}
+
 #[derive(Debug, Clone, DataSize)]
 pub struct PsFunc {
     pub ops: Vec<PsOp>
 }
 
+/// The stack limit given in the spec.
+const PS_STACK_LIMIT: usize = 100;
+
+struct PsStack(Vec<PsValue>);
+impl PsStack {
+    fn pop(&mut self) -> Result<PsValue, PostScriptError> {
+        self.0.pop().ok_or(PostScriptError::StackUnderflow)
+    }
+    fn num(&mut self) -> Result<f32, PostScriptError> {
+        match self.pop()? {
+            PsValue::Number(n) => Ok(n),
+            PsValue::Bool(_) => Err(PostScriptError::TypeCheck)
+        }
+    }
+    fn int(&mut self) -> Result<i32, PostScriptError> {
+        Ok(self.num()? as i32)
+    }
+    fn bool(&mut self) -> Result<bool, PostScriptError> {
+        match self.pop()? {
+            PsValue::Bool(b) => Ok(b),
+            PsValue::Number(_) => Err(PostScriptError::TypeCheck)
+        }
+    }
+    fn push(&mut self, v: PsValue) -> Result<(), PostScriptError> {
+        if self.0.len() >= PS_STACK_LIMIT {
+            return Err(PostScriptError::StackOverflow);
+        }
+        self.0.push(v);
+        Ok(())
+    }
+    fn push_num(&mut self, n: f32) -> Result<(), PostScriptError> {
+        self.push(PsValue::Number(n))
+    }
+    fn push_bool(&mut self, b: bool) -> Result<(), PostScriptError> {
+        self.push(PsValue::Bool(b))
+    }
+}
+
+// operands are listed bottom to top, `a b sub` computes `a - b`
 macro_rules! op {
-    ($stack:ident; $($v:ident),* => $($e:expr),*) => ( {
-        $(let $v = $stack.pop().ok_or(PostScriptError::StackUnderflow)?;)*
-        $($stack.push($e);)*
-    } )
+    ($stack:ident; $($v:ident),* => $e:expr) => ( {
+        op!(@pop $stack; $($v),*);
+        $stack.push_num($e)?;
+    } );
+    (@pop $stack:ident; ) => ();
+    (@pop $stack:ident; $v:ident $(, $rest:ident)*) => (
+        op!(@pop $stack; $($rest),*);
+        let $v = $stack.num()?;
+    );
 }
 
 impl PsFunc {
-    fn exec_inner(&self, stack: &mut Vec<f32>) -> Result<(), PostScriptError> {
-        for &op in &self.ops {
-            match op {
-                PsOp::Int(i) => stack.push(i as f32),
-                PsOp::Value(v) => stack.push(v),
-                PsOp::Dup => op!(stack; v => v, v),
-                PsOp::Exch => op!(stack; a, b => a, b),
+    fn exec_ops(ops: &[PsOp], stack: &mut PsStack) -> Result<(), PostScriptError> {
+        for op in ops {
+            match *op {
+                PsOp::Int(i) => stack.push_num(i as f32)?,
+                PsOp::Value(v) => stack.push_num(v)?,
+                PsOp::Bool(b) => stack.push_bool(b)?,
+
                 PsOp::Add => op!(stack; a, b => a + b),
                 PsOp::Sub => op!(stack; a, b => a - b),
                 PsOp::Mul => op!(stack; a, b => a * b),
+                PsOp::Div => op!(stack; a, b => a / b),
+                PsOp::Idiv => {
+                    let b = stack.int()?;
+                    let a = stack.int()?;
+                    stack.push_num(a.checked_div(b).ok_or(PostScriptError::TypeCheck)? as f32)?;
+                }
+                PsOp::Mod => {
+                    let b = stack.int()?;
+                    let a = stack.int()?;
+                    stack.push_num(a.checked_rem(b).ok_or(PostScriptError::TypeCheck)? as f32)?;
+                }
+                PsOp::Neg => op!(stack; a => -a),
                 PsOp::Abs => op!(stack; a => a.abs()),
-                PsOp::Roll => {
-                    let j = stack.pop().ok_or(PostScriptError::StackUnderflow)? as isize;
-                    let n = stack.pop().ok_or(PostScriptError::StackUnderflow)? as usize;
-                    let start = stack.len() - n;
-                    let slice = &mut stack[start..];
-                    if j > 0 {
-                        slice.rotate_right(j as usize);
+                PsOp::Ceiling => op!(stack; a => a.ceil()),
+                PsOp::Floor => op!(stack; a => a.floor()),
+                PsOp::Round => op!(stack; a => (a + 0.5).floor()),
+                PsOp::Truncate | PsOp::Cvi => op!(stack; a => a.trunc()),
+                PsOp::Cvr => op!(stack; a => a),
+                PsOp::Sqrt => op!(stack; a => a.sqrt()),
+                PsOp::Sin => op!(stack; a => a.to_radians().sin()),
+                PsOp::Cos => op!(stack; a => a.to_radians().cos()),
+                PsOp::Atan => op!(stack; num, den => num.atan2(den).to_degrees().rem_euclid(360.)),
+                PsOp::Exp => op!(stack; base, exponent => base.powf(exponent)),
+                PsOp::Ln => op!(stack; a => a.ln()),
+                PsOp::Log => op!(stack; a => a.log10()),
+
+                PsOp::Eq | PsOp::Ne => {
+                    let b = stack.pop()?;
+                    let a = stack.pop()?;
+                    let eq = match (a, b) {
+                        (PsValue::Number(a), PsValue::Number(b)) => a == b,
+                        (PsValue::Bool(a), PsValue::Bool(b)) => a == b,
+                        _ => false
+                    };
+                    stack.push_bool(eq == matches!(*op, PsOp::Eq))?;
+                }
+                PsOp::Gt | PsOp::Ge | PsOp::Lt | PsOp::Le => {
+                    let b = stack.num()?;
+                    let a = stack.num()?;
+                    stack.push_bool(match *op {
+                        PsOp::Gt => a > b,
+                        PsOp::Ge => a >= b,
+                        PsOp::Lt => a < b,
+                        _ => a <= b,
+                    })?;
+                }
+                PsOp::And | PsOp::Or | PsOp::Xor => {
+                    let b = stack.pop()?;
+                    let a = stack.pop()?;
+                    match (a, b) {
+                        (PsValue::Bool(a), PsValue::Bool(b)) => stack.push_bool(match *op {
+                            PsOp::And => a & b,
+                            PsOp::Or => a | b,
+                            _ => a ^ b,
+                        })?,
+                        (PsValue::Number(a), PsValue::Number(b)) => {
+                            let (a, b) = (a as i32, b as i32);
+                            stack.push_num(match *op {
+                                PsOp::And => a & b,
+                                PsOp::Or => a | b,
+                                _ => a ^ b,
+                            } as f32)?
+                        }
+                        _ => return Err(PostScriptError::TypeCheck)
+                    }
+                }
+                PsOp::Not => match stack.pop()? {
+                    PsValue::Bool(b) => stack.push_bool(!b)?,
+                    PsValue::Number(n) => stack.push_num(!(n as i32) as f32)?,
+                },
+                PsOp::Bitshift => {
+                    let shift = stack.int()?;
+                    let a = stack.int()?;
+                    let r = if shift >= 0 {
+                        a.checked_shl(shift as u32).unwrap_or(0)
+                    } else {
+                        a.checked_shr(-shift as u32).unwrap_or(0)
+                    };
+                    stack.push_num(r as f32)?;
+                }
+
+                PsOp::If(ref then) => {
+                    if stack.bool()? {
+                        PsFunc::exec_ops(then, stack)?;
+                    }
+                }
+                PsOp::IfElse(ref then, ref otherwise) => {
+                    if stack.bool()? {
+                        PsFunc::exec_ops(then, stack)?;
                     } else {
-                        slice.rotate_left(-j as usize);
+                        PsFunc::exec_ops(otherwise, stack)?;
                     }
                 }
-                PsOp::Index => {
-                    let n = stack.pop().ok_or(PostScriptError::StackUnderflow)? as usize;
-                    if n >= stack.len() { return Err(PostScriptError::StackUnderflow); }
-                    let val = stack[stack.len() - n - 1];
-                    stack.push(val);
+
+                PsOp::Dup => {
+                    let v = stack.pop()?;
+                    stack.push(v)?;
+                    stack.push(v)?;
+                }
+                PsOp::Exch => {
+                    let b = stack.pop()?;
+                    let a = stack.pop()?;
+                    stack.push(b)?;
+                    stack.push(a)?;
                 }
-                PsOp::Cvr => {}
                 PsOp::Pop => {
-                    stack.pop().ok_or(PostScriptError::StackUnderflow)?;
+                    stack.pop()?;
+                }
+                PsOp::Copy => {
+                    let n = stack.int()?;
+                    let start = usize::try_from(n).ok()
+                        .and_then(|n| stack.0.len().checked_sub(n))
+                        .ok_or(PostScriptError::StackUnderflow)?;
+                    for i in start .. stack.0.len() {
+                        let v = stack.0[i];
+                        stack.push(v)?;
+                    }
+                }
+                PsOp::Index => {
+                    let n = stack.int()?;
+                    let idx = usize::try_from(n).ok()
+                        .and_then(|n| stack.0.len().checked_sub(n + 1))
+                        .ok_or(PostScriptError::StackUnderflow)?;
+                    let v = stack.0[idx];
+                    stack.push(v)?;
+                }
+                PsOp::Roll => {
+                    let j = stack.int()?;
+                    let n = stack.int()?;
+                    let start = usize::try_from(n).ok()
+                        .and_then(|n| stack.0.len().checked_sub(n))
+                        .ok_or(PostScriptError::StackUnderflow)?;
+                    let slice = &mut stack.0[start..];
+                    if !slice.is_empty() {
+                        let j = j.rem_euclid(slice.len() as i32) as usize;
+                        slice.rotate_right(j);
+                    }
                 }
             }
         }
         Ok(())
     }
     pub fn exec(&self, input: &[f32], output: &mut [f32]) -> Result<()> {
-        let mut stack = Vec::with_capacity(10);
-        stack.extend_from_slice(input);
-        match self.exec_inner(&mut stack) {
-            Ok(()) => {},
-            Err(_) => return Err(PdfError::PostScriptExec)
+        let mut stack = PsStack(Vec::with_capacity(10));
+        stack.0.extend(input.iter().map(|&x| PsValue::Number(x)));
+        if PsFunc::exec_ops(&self.ops, &mut stack).is_err() {
+            return Err(PdfError::PostScriptExec);
         }
-        if output.len() != stack.len() {
-            bail!("incorrect output length: expected {}, found {}.", stack.len(), output.len())
+        if output.len() != stack.0.len() {
+            bail!("incorrect output length: expected {}, found {}.", stack.0.len(), output.len())
+        }
+        for (out, v) in output.iter_mut().zip(stack.0) {
+            *out = match v {
+                PsValue::Number(n) => n,
+                PsValue::Bool(_) => return Err(PdfError::PostScriptExec)
+            };
         }
-        output.copy_from_slice(&stack);
         Ok(())
     }
     pub fn parse(s: &str) -> Result<Self, PdfError> {
         let start = s.find('{').ok_or(PdfError::PostScriptParse)?;
         let end = s.rfind('}').ok_or(PdfError::PostScriptParse)?;
+        if end < start {
+            return Err(PdfError::PostScriptParse);
+        }
 
-        let ops: Result<Vec<_>, _> = s[start + 1 .. end].split_ascii_whitespace().map(PsOp::parse).collect();
-        Ok(PsFunc { ops: ops? })
+        let spaced = s[start + 1 .. end].replace('{', " { ").replace('}', " } ");
+        let mut tokens = spaced.split_ascii_whitespace();
+        let ops = PsFunc::parse_block(&mut tokens, false)?;
+        Ok(PsFunc { ops })
+    }
+    /// Parse ops up to the closing `}` of a procedure (or the end of input at the top level).
+    fn parse_block<'a>(tokens: &mut impl Iterator<Item=&'a str>, nested: bool) -> Result<Vec<PsOp>> {
+        let mut ops = vec![];
+        // procedures directly preceding `if`/`ifelse`
+        let mut procs: Vec<Vec<PsOp>> = vec![];
+        while let Some(token) = tokens.next() {
+            match token {
+                "{" => {
+                    procs.push(PsFunc::parse_block(tokens, true)?);
+                    continue;
+                }
+                "}" if nested => {
+                    if !procs.is_empty() {
+                        return Err(PdfError::PostScriptParse);
+                    }
+                    return Ok(ops);
+                }
+                "if" => match (procs.pop(), procs.is_empty()) {
+                    (Some(then), true) => ops.push(PsOp::If(then)),
+                    _ => return Err(PdfError::PostScriptParse)
+                },
+                "ifelse" => match (procs.pop(), procs.pop(), procs.is_empty()) {
+                    (Some(otherwise), Some(then), true) => ops.push(PsOp::IfElse(then, otherwise)),
+                    _ => return Err(PdfError::PostScriptParse)
+                },
+                _ if !procs.is_empty() => return Err(PdfError::PostScriptParse),
+                token => ops.push(PsOp::parse(token)?)
+            }
+        }
+        if nested || !procs.is_empty() {
+            return Err(PdfError::PostScriptParse);
+        }
+        Ok(ops)
     }
 }
 
-#[derive(Copy, Clone, Debug, DataSize)]
+#[derive(Clone, Debug)]
 pub enum PsOp {
     Int(i32),
     Value(f32),
+    Bool(bool),
+
+    // arithmetic
     Add,
     Sub,
-    Abs,
     Mul,
-    Dup,
-    Exch,
-    Roll,
-    Index,
+    Div,
+    Idiv,
+    Mod,
+    Neg,
+    Abs,
+    Ceiling,
+    Floor,
+    Round,
+    Truncate,
+    Sqrt,
+    Sin,
+    Cos,
+    Atan,
+    Exp,
+    Ln,
+    Log,
+    Cvi,
     Cvr,
+
+    // relational, boolean and bitwise
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    And,
+    Or,
+    Xor,
+    Not,
+    Bitshift,
+
+    // conditionals
+    If(Vec<PsOp>),
+    IfElse(Vec<PsOp>, Vec<PsOp>),
+
+    // stack
     Pop,
+    Exch,
+    Dup,
+    Copy,
+    Index,
+    Roll,
+}
+impl DataSize for PsOp {
+    const IS_DYNAMIC: bool = true;
+    const STATIC_HEAP_SIZE: usize = 0;
+
+    #[inline]
+    fn estimate_heap_size(&self) -> usize {
+        let block = |ops: &Vec<PsOp>| {
+            ops.capacity() * std::mem::size_of::<PsOp>() + ops.iter().map(|op| op.estimate_heap_size()).sum::<usize>()
+        };
+        match *self {
+            PsOp::If(ref then) => block(then),
+            PsOp::IfElse(ref then, ref otherwise) => block(then) + block(otherwise),
+            _ => 0
+        }
+    }
 }
 impl PsOp {
     pub fn parse(s: &str) -> Result<Self> {
@@ -428,16 +773,46 @@ impl PsOp {
             Ok(PsOp::Value(f))
         } else {
             Ok(match s {
+                "true" => PsOp::Bool(true),
+                "false" => PsOp::Bool(false),
                 "add" => PsOp::Add,
                 "sub" => PsOp::Sub,
-                "abs" => PsOp::Abs,
                 "mul" => PsOp::Mul,
-                "dup" => PsOp::Dup,
-                "exch" => PsOp::Exch,
-                "roll" => PsOp::Roll,
-                "index" => PsOp::Index,
+                "div" => PsOp::Div,
+                "idiv" => PsOp::Idiv,
+                "mod" => PsOp::Mod,
+                "neg" => PsOp::Neg,
+                "abs" => PsOp::Abs,
+                "ceiling" => PsOp::Ceiling,
+                "floor" => PsOp::Floor,
+                "round" => PsOp::Round,
+                "truncate" => PsOp::Truncate,
+                "sqrt" => PsOp::Sqrt,
+                "sin" => PsOp::Sin,
+                "cos" => PsOp::Cos,
+                "atan" => PsOp::Atan,
+                "exp" => PsOp::Exp,
+                "ln" => PsOp::Ln,
+                "log" => PsOp::Log,
+                "cvi" => PsOp::Cvi,
                 "cvr" => PsOp::Cvr,
+                "eq" => PsOp::Eq,
+                "ne" => PsOp::Ne,
+                "gt" => PsOp::Gt,
+                "ge" => PsOp::Ge,
+                "lt" => PsOp::Lt,
+                "le" => PsOp::Le,
+                "and" => PsOp::And,
+                "or" => PsOp::Or,
+                "xor" => PsOp::Xor,
+                "not" => PsOp::Not,
+                "bitshift" => PsOp::Bitshift,
                 "pop" => PsOp::Pop,
+                "exch" => PsOp::Exch,
+                "dup" => PsOp::Dup,
+                "copy" => PsOp::Copy,
+                "index" => PsOp::Index,
+                "roll" => PsOp::Roll,
                 _ => {
                     bail!("unimplemented op {}", s);
                 }
@@ -445,3 +820,111 @@ impl PsOp {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear(c0: f32, c1: f32) -> Function {
+        Function::Interpolated(vec![InterpolatedFunctionDim {
+            input_range: (0., 1.),
+            output_range: (0., 1.),
+            c0,
+            c1,
+            exponent: 1.0,
+        }])
+    }
+
+    fn ps(code: &str, input: &[f32], n: usize) -> Vec<f32> {
+        let f = PsFunc::parse(code).unwrap();
+        let mut out = vec![0.; n];
+        f.exec(input, &mut out).unwrap();
+        out
+    }
+
+    fn sampled(size: &[usize], bits: u8, data: &[u8], range: Vec<f32>) -> SampledFunction {
+        let max = ((1u64 << bits) - 1) as f32;
+        SampledFunction {
+            input: size.iter().map(|&size| SampledFunctionInput {
+                domain: (0., 1.),
+                encode_offset: 0.,
+                encode_scale: (size - 1) as f32,
+                size,
+            }).collect(),
+            output: range.chunks_exact(2).map(|r| SampledFunctionOutput { offset: r[0], scale: (r[1] - r[0]) / max }).collect(),
+            data: data.into(),
+            bits_per_sample: bits,
+            order: Interpolation::Linear,
+            range,
+        }
+    }
+
+    #[test]
+    fn test_sampled() {
+        // 1-in, 1-out with 4 bit samples 0, 15, 0
+        let f = sampled(&[3], 4, &[0x0F, 0x00], vec![0., 1.]);
+        let mut out = [0.];
+        for (x, y) in [(0., 0.), (0.25, 0.5), (0.5, 1.), (1., 0.), (2., 0.)] {
+            f.apply(&[x], &mut out).unwrap();
+            assert!((out[0] - y).abs() < 1e-6, "f({}) = {} != {}", x, out[0], y);
+        }
+
+        // 2-in, 1-out with 16 bit samples on a 2x2 grid
+        let f = sampled(&[2, 2], 16, &[0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0], vec![0., 1.]);
+        f.apply(&[0.5, 0.5], &mut out).unwrap();
+        assert!((out[0] - 0.5).abs() < 1e-6);
+        f.apply(&[1., 0.], &mut out).unwrap();
+        assert_eq!(out[0], 1.);
+        f.apply(&[1., 1.], &mut out).unwrap();
+        assert_eq!(out[0], 0.);
+    }
+
+    #[test]
+    fn test_postscript() {
+        assert_eq!(ps("{ 3 sub }", &[5.], 1), [2.]);
+        assert_eq!(ps("{ exch 2 div }", &[1., 4.], 2), [4., 0.5]);
+        assert_eq!(ps("{ 7 2 idiv 7 2 mod -7 neg }", &[], 3), [3., 1., 7.]);
+        assert_eq!(ps("{ 1 2 3 3 1 roll }", &[], 3), [3., 1., 2.]);
+        assert_eq!(ps("{ 1 2 2 copy 3 index }", &[], 5), [1., 2., 1., 2., 1.]);
+        assert_eq!(ps("{ 1 4 bitshift 12 10 and true not {1} {0} ifelse }", &[], 3), [16., 8., 0.]);
+        assert_eq!(ps("{0 1 atan 4 2 exp 100 log}", &[], 3), [0., 16., 2.]);
+
+        // tint transform of a spot color into CMYK
+        let tint = "{ dup 0.5 gt { 1 exch sub 0 0 } { 0.2 mul 0 0 } ifelse 0 }";
+        assert_eq!(ps(tint, &[0.75], 4), [0.25, 0., 0., 0.]);
+        assert_eq!(ps(tint, &[0.25], 4), [0.05, 0., 0., 0.]);
+
+        assert!(PsFunc::parse("{ 1 { 2 } }").is_err());
+        assert!(PsFunc::parse("{ 1 2 if }").is_err());
+        let f = PsFunc::parse("{ pop pop }").unwrap();
+        assert!(f.exec(&[1.], &mut []).is_err());
+    }
+
+    #[test]
+    fn test_stiching() {
+        let f = Function::Stiching(StichingFunction {
+            domain: (0., 1.),
+            functions: vec![linear(0., 1.), linear(1., 0.)],
+            bounds: vec![0.5],
+            encode: vec![0., 1., 0., 1.],
+        });
+        let mut out = [0.];
+        for &(x, y) in &[(0., 0.), (0.25, 0.5), (0.5, 1.), (0.75, 0.5), (1., 0.), (2., 0.)] {
+            f.apply(&[x], &mut out).unwrap();
+            assert!((out[0] - y).abs() < 1e-6, "f({}) = {}, expected {}", x, out[0], y);
+        }
+
+        // a two-stop gradient as written in a shading dictionary
+        let data = b"<< /FunctionType 3 /Domain [0 1] /Bounds [0.5] /Encode [0 1 0 1] /Functions [
+            << /FunctionType 2 /Domain [0 1] /C0 [0] /C1 [1] /N 1 >>
+            << /FunctionType 2 /Domain [0 1] /C0 [1] /C1 [0] /N 1 >> ] >>";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        let f = Function::from_primitive(p, &NoResolve).unwrap();
+        f.apply(&[0.75], &mut out).unwrap();
+        assert!((out[0] - 0.5).abs() < 1e-6);
+
+        let data = b"<< /FunctionType 3 /Domain [0] /Bounds [] /Encode [0 1] /Functions [] >>";
+        let p = crate::parser::parse(data, &NoResolve, crate::parser::ParseFlags::ANY).unwrap();
+        assert!(Function::from_primitive(p, &NoResolve).is_err());
+    }
+}
@@ -25,6 +25,7 @@ use std::sync::Arc;
 use std::ops::{Deref, Range};
 use std::hash::{Hash, Hasher};
 use std::convert::TryInto;
+use std::path::PathBuf;
 use datasize::{DataSize};
 
 pub type ObjNr = u64;
@@ -35,6 +36,10 @@ pub struct ParseOptions {
     pub allow_xref_error: bool,
     pub allow_invalid_ops: bool,
     pub allow_missing_endobj: bool,
+    /// how many objects may be nested while parsing (for example fonts in resources in fonts ...)
+    pub max_nesting_depth: usize,
+    /// directory with the predefined CMaps named by Type0 fonts (none are bundled), see `CMap::predefined`
+    pub cmap_dir: Option<PathBuf>,
 }
 impl ParseOptions {
     pub const fn tolerant() -> Self {
@@ -43,6 +48,8 @@ impl ParseOptions {
             allow_xref_error: true,
             allow_invalid_ops: true,
             allow_missing_endobj: true,
+            max_nesting_depth: 64,
+            cmap_dir: None,
         }
     }
     pub const fn strict() -> Self {
@@ -51,6 +58,8 @@ impl ParseOptions {
             allow_xref_error: false,
             allow_invalid_ops: true,
             allow_missing_endobj: false,
+            max_nesting_depth: 64,
+            cmap_dir: None,
         }
     }
 }
@@ -7,7 +7,7 @@ use crate as pdf;
 use crate::object::*;
 use crate::error::*;
 use crate::content::{Content, FormXObject, Matrix, parse_ops, serialize_ops, Op};
-use crate::font::Font;
+use crate::font::{Font, FontInfo};
 use crate::enc::StreamFilter;
 
 /// Node in a page tree - type is either `Page` or `PageTree`
@@ -168,6 +168,9 @@ pub struct PageTree {
     
     #[pdf(key="CropBox")]
     pub crop_box:   Option<Rect>,
+
+    #[pdf(key="Rotate")]
+    pub rotate: Option<i32>,
 }
 impl PageTree {
     pub fn page(&self, resolve: &impl Resolve, page_nr: u32) -> Result<PageRc> {
@@ -258,8 +261,12 @@ pub struct Page {
     #[pdf(key="Contents")]
     pub contents:   Option<Content>,
 
-    #[pdf(key="Rotate", default="0")]
-    pub rotate: i32,
+    #[pdf(key="Rotate")]
+    pub rotate: Option<i32>,
+
+    /// the raw `/Annots` array, see `Page::annotations`
+    #[pdf(key="Annots")]
+    pub annots: Option<Primitive>,
 }
 fn inherit<'a, T: 'a, F>(mut parent: &'a PageTree, f: F) -> Result<Option<T>>
     where F: Fn(&'a PageTree) -> Option<T>
@@ -282,7 +289,8 @@ impl Page {
             trim_box:   None,
             resources:  None,
             contents:   None,
-            rotate:     0,
+            rotate:     None,
+            annots:     None,
         }
     }
     pub fn media_box(&self) -> Result<Rect> {
@@ -301,6 +309,23 @@ impl Page {
             }
         }
     }
+    /// Clockwise rotation of the page in degrees, normalized to 0, 90, 180 or 270.
+    ///
+    /// Inherited from the page tree if the page has no `/Rotate` entry.
+    pub fn rotation(&self) -> Result<i32> {
+        let rotate = match self.rotate {
+            Some(r) => r,
+            None => inherit(&self.parent, |pt| pt.rotate)?.unwrap_or(0)
+        };
+        Ok(normalize_rotation(rotate))
+    }
+    /// The visible region of the page: the crop box clipped to the media box.
+    ///
+    /// Falls back to the media box if there is no crop box or it lies outside the media box.
+    pub fn view_box(&self) -> Result<Rect> {
+        let media_box = self.media_box()?.normalized();
+        Ok(self.crop_box()?.intersect(media_box).unwrap_or(media_box))
+    }
     pub fn resources(&self) -> Result<&MaybeRef<Resources>> {
         match self.resources {
             Some(ref r) => Ok(r),
@@ -308,6 +333,20 @@ impl Page {
                 .ok_or_else(|| PdfError::MissingEntry { typ: "Page", field: "Resources".into() })
         }
     }
+    /// The annotations of the page. Malformed entries are skipped with a warning.
+    pub fn annotations(&self, resolve: &impl Resolve) -> Result<Vec<MaybeRef<Annot>>> {
+        let annots = match self.annots {
+            Some(ref p) => p.clone().resolve(resolve)?.into_array()?,
+            None => return Ok(vec![])
+        };
+        Ok(annots.into_iter().filter_map(|p| match MaybeRef::from_primitive(p, resolve) {
+            Ok(annot) => Some(annot),
+            Err(e) => {
+                warn!("skipping malformed annotation: {:?}", e);
+                None
+            }
+        }).collect())
+    }
 }
 impl SubType<PagesNode> for Page {}
 
@@ -334,7 +373,10 @@ pub struct Resources {
     #[pdf(key="Pattern")]
     pub pattern: HashMap<Name, Ref<Pattern>>,
 
-    // shading: Option<Shading>,
+    /// raw entries of `/Shading`, see `Resources::shading`
+    #[pdf(key="Shading")]
+    pub shadings: HashMap<Name, Primitive>,
+
     #[pdf(key="XObject")]
     pub xobjects: HashMap<Name, Ref<XObject>>,
     // /XObject is a dictionary that map arbitrary names to XObjects
@@ -348,16 +390,81 @@ impl Resources {
     pub fn fonts(&self) -> impl Iterator<Item=(&str, &MaybeRef<Font>)> {
         self.fonts.iter().map(|(k, v)| (k.as_str(), v))
     }
+    /// The fonts of the `/Font` dictionary sorted by resource name, followed by those set by
+    /// `/ExtGState` entries in the order of the state names.
+    ///
+    /// Fonts of graphics states that can't be loaded are skipped with a warning.
+    pub fn font_info(&self, resolve: &impl Resolve) -> Vec<FontInfo> {
+        let mut list: Vec<_> = self.fonts.iter()
+            .map(|(name, font)| font.font_info(Some(name.clone())))
+            .collect();
+        list.sort_by(|a, b| a.resource_name.cmp(&b.resource_name));
+        let mut gs_fonts: Vec<_> = self.graphics_states.iter()
+            .filter_map(|(name, gs)| Some((name, gs.font?.0)))
+            .collect();
+        gs_fonts.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, font) in gs_fonts {
+            match resolve.get(font) {
+                Ok(font) => list.push(font.font_info(None)),
+                Err(e) => warn!("skipping the font of ExtGState /{}: {:?}", name, e)
+            }
+        }
+        list
+    }
+    /// Parse the shading used by `sh`.
+    pub fn shading(&self, name: &str, resolve: &impl Resolve) -> Result<MaybeRef<Shading>> {
+        let p = self.shadings.get(name)
+            .ok_or_else(|| PdfError::MissingResource { kind: "Shading", name: name.into() })?;
+        MaybeRef::from_primitive(p.clone(), resolve)
+    }
+    pub fn xobject(&self, name: &str) -> Result<Ref<XObject>> {
+        self.xobjects.get(name).copied()
+            .ok_or_else(|| PdfError::MissingResource { kind: "XObject", name: name.into() })
+    }
+    pub fn font(&self, name: &str) -> Result<&MaybeRef<Font>> {
+        self.fonts.get(name)
+            .ok_or_else(|| PdfError::MissingResource { kind: "Font", name: name.into() })
+    }
+    /// Look up the color space used by `cs`/`CS`.
+    ///
+    /// Device color space names are handled without consulting the resource dictionary.
+    pub fn color_space(&self, name: &str) -> Result<ColorSpace> {
+        if let Some(cs) = self.color_spaces.get(name) {
+            return Ok(cs.clone());
+        }
+        ColorSpace::device(name)
+            .ok_or_else(|| PdfError::MissingResource { kind: "ColorSpace", name: name.into() })
+    }
+    pub fn graphics_state(&self, name: &str) -> Result<&GraphicsStateParameters> {
+        self.graphics_states.get(name)
+            .ok_or_else(|| PdfError::MissingResource { kind: "ExtGState", name: name.into() })
+    }
 }
 
 
+#[derive(Object, ObjectWrite, Debug, Copy, Clone, PartialEq, DataSize)]
+pub enum PaintType {
+    /// the pattern's content stream specifies the colors
+    Colored = 1,
+    /// the color is given by the `scn`/`SCN` operands when the pattern is used
+    Uncolored = 2,
+}
+
+#[derive(Object, ObjectWrite, Debug, Copy, Clone, PartialEq, DataSize)]
+pub enum TilingType {
+    ConstantSpacing = 1,
+    NoDistortion = 2,
+    ConstantSpacingFaster = 3,
+}
+
+/// Tiling pattern dictionary
 #[derive(Debug, Object, ObjectWrite, DataSize, Clone)]
 pub struct PatternDict {
     #[pdf(key="PaintType")]
-    pub paint_type: Option<i32>,
+    pub paint_type: Option<PaintType>,
 
     #[pdf(key="TilingType")]
-    pub tiling_type: Option<i32>,
+    pub tiling_type: Option<TilingType>,
 
     #[pdf(key="BBox")]
     pub bbox: Rect,
@@ -369,22 +476,56 @@ pub struct PatternDict {
     pub y_step: f32,
 
     #[pdf(key="Resources")]
-    pub resources: Ref<Resources>,
+    pub resources: MaybeRef<Resources>,
 
+    /// maps pattern space to the default coordinate space of the pattern's parent. Identity if absent.
     #[pdf(key="Matrix")]
     pub matrix: Option<Matrix>,
 }
 
+/// Shading pattern (`/PatternType 2`)
+#[derive(Debug, Object, DataSize)]
+pub struct ShadingPatternDict {
+    #[pdf(key="Shading")]
+    pub shading: MaybeRef<Shading>,
+
+    #[pdf(key="Matrix")]
+    pub matrix: Option<Matrix>,
+
+    #[pdf(key="ExtGState")]
+    pub graphics_state: Option<MaybeRef<GraphicsStateParameters>>,
+
+    #[pdf(other)]
+    pub other: Dictionary,
+}
+impl ObjectWrite for ShadingPatternDict {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
+        let mut dict = self.other.clone();
+        dict.insert("PatternType", Primitive::Integer(2));
+        dict.insert("Shading", self.shading.to_primitive(update)?);
+        if let Some(ref matrix) = self.matrix {
+            dict.insert("Matrix", matrix.to_primitive(update)?);
+        }
+        if let Some(ref gs) = self.graphics_state {
+            dict.insert("ExtGState", gs.to_primitive(update)?);
+        }
+        Ok(Primitive::Dictionary(dict))
+    }
+}
+
 #[derive(Debug, DataSize)]
 pub enum Pattern {
     Dict(PatternDict),
     Stream(PatternDict, Vec<Op>),
+    Shading(ShadingPatternDict),
 }
 impl Pattern {
-    pub fn dict(&self) -> &PatternDict {
+    /// The tiling pattern dictionary, or `None` for a shading pattern.
+    pub fn dict(&self) -> Option<&PatternDict> {
         match *self {
-            Pattern::Dict(ref d) => d,
-            Pattern::Stream(ref d, _) => d,
+            Pattern::Dict(ref d) => Some(d),
+            Pattern::Stream(ref d, _) => Some(d),
+            Pattern::Shading(_) => None,
         }
     }
 }
@@ -392,7 +533,12 @@ impl Object for Pattern {
     fn from_primitive(p: Primitive, resolve: &impl Resolve) -> Result<Self> {
         let p = p.resolve(resolve)?;
         match p {
-            Primitive::Dictionary(dict) => Ok(Pattern::Dict(PatternDict::from_dict(dict, resolve)?)),
+            Primitive::Dictionary(dict) => {
+                match dict.get("PatternType").map(|p| p.as_integer()).transpose()? {
+                    Some(2) => Ok(Pattern::Shading(t!(ShadingPatternDict::from_dict(dict, resolve)))),
+                    _ => Ok(Pattern::Dict(t!(PatternDict::from_dict(dict, resolve)))),
+                }
+            }
             Primitive::Stream(s) => {
                 let stream: Stream<PatternDict> = Stream::from_stream(s, resolve)?;
                 let data = stream.data(resolve)?;
@@ -413,6 +559,139 @@ impl ObjectWrite for Pattern {
                 let stream = Stream::new_with_filters(d.clone(), data, vec![]);
                 stream.to_primitive(update)
             }
+            Pattern::Shading(ref d) => d.to_primitive(update),
+        }
+    }
+}
+
+#[derive(Object, ObjectWrite, Debug, Copy, Clone, PartialEq, DataSize)]
+pub enum ShadingType {
+    Function = 1,
+    Axial = 2,
+    Radial = 3,
+    FreeForm = 4,
+    Lattice = 5,
+    Coons = 6,
+    TensorProduct = 7,
+}
+
+/// Shading dictionary
+/// 
+/// The entries specific to a shading type are optional here; which ones are present depends on `shading_type`.
+#[derive(Object, Debug, DataSize)]
+pub struct ShadingDict {
+    #[pdf(key="ShadingType")]
+    pub shading_type: ShadingType,
+
+    #[pdf(key="ColorSpace")]
+    pub color_space: ColorSpace,
+
+    #[pdf(key="Background")]
+    pub background: Option<Vec<f32>>,
+
+    #[pdf(key="BBox")]
+    pub bbox: Option<Rect>,
+
+    #[pdf(key="AntiAlias", default="false")]
+    pub anti_alias: bool,
+
+    /// `[x0 y0 x1 y1]` for axial and `[x0 y0 r0 x1 y1 r1]` for radial shadings
+    #[pdf(key="Coords")]
+    pub coords: Option<Vec<f32>>,
+
+    /// defaults to `[0 1]` for axial and radial shadings
+    #[pdf(key="Domain")]
+    pub domain: Option<Vec<f32>>,
+
+    #[pdf(key="Matrix")]
+    pub matrix: Option<Matrix>,
+
+    /// either a single function with one output per color component, or one function per component
+    #[pdf(key="Function")]
+    pub function: Option<Vec<Function>>,
+
+    /// whether to extend the shading beyond the start and end points
+    #[pdf(key="Extend")]
+    pub extend: Option<Vec<bool>>,
+
+    #[pdf(other)]
+    pub other: Dictionary,
+}
+impl ObjectWrite for ShadingDict {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
+        self.to_dict(update).map(Primitive::Dictionary)
+    }
+}
+/// Keeps the entries in `other`, which hold the parameters of mesh shadings.
+impl ToDict for ShadingDict {
+    fn to_dict(&self, update: &mut impl Updater) -> Result<Dictionary> {
+        let mut dict = self.other.clone();
+        dict.insert("ShadingType", self.shading_type.to_primitive(update)?);
+        dict.insert("ColorSpace", self.color_space.to_primitive(update)?);
+        if let Some(ref background) = self.background {
+            dict.insert("Background", background.to_primitive(update)?);
+        }
+        if let Some(ref bbox) = self.bbox {
+            dict.insert("BBox", bbox.to_primitive(update)?);
+        }
+        if self.anti_alias {
+            dict.insert("AntiAlias", Primitive::Boolean(true));
+        }
+        if let Some(ref coords) = self.coords {
+            dict.insert("Coords", coords.to_primitive(update)?);
+        }
+        if let Some(ref domain) = self.domain {
+            dict.insert("Domain", domain.to_primitive(update)?);
+        }
+        if let Some(ref matrix) = self.matrix {
+            dict.insert("Matrix", matrix.to_primitive(update)?);
+        }
+        // a single function is written directly, as it may have several outputs
+        match self.function {
+            Some(ref functions) if functions.len() == 1 => {
+                dict.insert("Function", functions[0].to_primitive(update)?);
+            }
+            Some(ref functions) => {
+                dict.insert("Function", functions.to_primitive(update)?);
+            }
+            None => {}
+        }
+        if let Some(ref extend) = self.extend {
+            dict.insert("Extend", extend.to_primitive(update)?);
+        }
+        Ok(dict)
+    }
+}
+
+#[derive(Debug, DataSize)]
+#[allow(clippy::large_enum_variant)]
+pub enum Shading {
+    Dict(ShadingDict),
+    Stream(Stream<ShadingDict>),
+}
+impl Shading {
+    pub fn dict(&self) -> &ShadingDict {
+        match *self {
+            Shading::Dict(ref d) => d,
+            Shading::Stream(ref s) => &s.info.info,
+        }
+    }
+}
+impl Object for Shading {
+    fn from_primitive(p: Primitive, resolve: &impl Resolve) -> Result<Self> {
+        let p = p.resolve(resolve)?;
+        match p {
+            Primitive::Dictionary(dict) => Ok(Shading::Dict(t!(ShadingDict::from_dict(dict, resolve)))),
+            Primitive::Stream(s) => Ok(Shading::Stream(t!(Stream::from_stream(s, resolve)))),
+            p => Err(PdfError::UnexpectedPrimitive { expected: "Dictionary or Stream", found: p.get_debug_name() })
+        }
+    }
+}
+impl ObjectWrite for Shading {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
+        match *self {
+            Shading::Dict(ref d) => d.to_primitive(update),
+            Shading::Stream(ref s) => s.to_primitive(update),
         }
     }
 }
@@ -452,12 +731,15 @@ pub struct GraphicsStateParameters {
     #[pdf(key="RI")]
     pub rendering_intent: Option<Name>,
 
+    /// overprint for stroking (and, unless `op` is given, all other) operations.
     #[pdf(key="OP")]
     pub overprint: Option<bool>,
 
+    /// overprint for non-stroking operations. Same as `OP` if absent.
     #[pdf(key="op")]
     pub overprint_fill: Option<bool>,
 
+    /// 1 selects nonzero overprint mode: zero CMYK components leave the backdrop unchanged.
     #[pdf(key="OPM")]
     pub overprint_mode: Option<i32>,
 
@@ -476,10 +758,10 @@ pub struct GraphicsStateParameters {
     // SA
 
     #[pdf(key="BM")]
-    pub blend_mode: Option<Primitive>,
+    pub blend_mode: Option<BlendMode>,
 
     #[pdf(key="SMask")]
-    pub smask: Option<Primitive>,
+    pub smask: Option<SoftMask>,
 
     
     #[pdf(key="CA")]
@@ -497,6 +779,144 @@ pub struct GraphicsStateParameters {
     #[pdf(other)]
     _other: Dictionary
 }
+#[derive(ObjectWrite, Debug, Copy, Clone, PartialEq, Eq, DataSize)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Hue,
+    Saturation,
+    Color,
+    Luminosity,
+}
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        Some(match name {
+            // `Compatible` is a deprecated alias of `Normal`
+            "Normal" | "Compatible" => BlendMode::Normal,
+            "Multiply" => BlendMode::Multiply,
+            "Screen" => BlendMode::Screen,
+            "Overlay" => BlendMode::Overlay,
+            "Darken" => BlendMode::Darken,
+            "Lighten" => BlendMode::Lighten,
+            "ColorDodge" => BlendMode::ColorDodge,
+            "ColorBurn" => BlendMode::ColorBurn,
+            "HardLight" => BlendMode::HardLight,
+            "SoftLight" => BlendMode::SoftLight,
+            "Difference" => BlendMode::Difference,
+            "Exclusion" => BlendMode::Exclusion,
+            "Hue" => BlendMode::Hue,
+            "Saturation" => BlendMode::Saturation,
+            "Color" => BlendMode::Color,
+            "Luminosity" => BlendMode::Luminosity,
+            _ => return None
+        })
+    }
+    /// Whether the mode works on each color component independently.
+    pub fn is_separable(self) -> bool {
+        !matches!(self, BlendMode::Hue | BlendMode::Saturation | BlendMode::Color | BlendMode::Luminosity)
+    }
+}
+impl Object for BlendMode {
+    /// Also accepts the deprecated array form, taking the first recognized mode.
+    /// Anything unrecognized is treated as `Normal`.
+    fn from_primitive(p: Primitive, resolve: &impl Resolve) -> Result<Self> {
+        match p.resolve(resolve) {
+            Ok(Primitive::Name(name)) => Ok(BlendMode::from_name(&name).unwrap_or_else(|| {
+                warn!("unknown blend mode /{}", name);
+                BlendMode::Normal
+            })),
+            Ok(Primitive::Array(arr)) => Ok(arr.iter()
+                .filter_map(|p| p.as_name().ok())
+                .find_map(BlendMode::from_name)
+                .unwrap_or(BlendMode::Normal)),
+            Ok(p) => {
+                warn!("invalid blend mode {:?}", p);
+                Ok(BlendMode::Normal)
+            }
+            Err(e) => {
+                warn!("invalid blend mode: {:?}", e);
+                Ok(BlendMode::Normal)
+            }
+        }
+    }
+}
+
+/// The `/SMask` entry of an ExtGState.
+#[derive(Debug, DataSize)]
+pub enum SoftMask {
+    /// `/None`: removes the current soft mask
+    None,
+    Mask(SoftMaskDict),
+}
+impl Object for SoftMask {
+    /// An invalid mask dictionary is replaced by `/None` with a warning.
+    fn from_primitive(p: Primitive, resolve: &impl Resolve) -> Result<Self> {
+        let mask = match p.resolve(resolve) {
+            Ok(Primitive::Name(ref name)) if name == "None" => return Ok(SoftMask::None),
+            Ok(p) => SoftMaskDict::from_primitive(p, resolve),
+            Err(e) => Err(e),
+        };
+        Ok(mask.map(SoftMask::Mask).unwrap_or_else(|e| {
+            warn!("invalid soft mask, using /None: {:?}", e);
+            SoftMask::None
+        }))
+    }
+}
+impl ObjectWrite for SoftMask {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
+        match *self {
+            SoftMask::None => Ok(Primitive::name("None")),
+            SoftMask::Mask(ref dict) => dict.to_primitive(update),
+        }
+    }
+}
+
+#[derive(Object, ObjectWrite, Debug, Copy, Clone, PartialEq, Eq, DataSize)]
+pub enum SoftMaskType {
+    /// the group's alpha is used as the mask
+    Alpha,
+    /// the group's luminosity is used as the mask
+    Luminosity,
+}
+
+#[derive(Object, ObjectWrite, Debug, DataSize)]
+#[pdf(Type="Mask?")]
+pub struct SoftMaskDict {
+    #[pdf(key="S")]
+    pub subtype: SoftMaskType,
+
+    /// transparency group that defines the mask
+    #[pdf(key="G")]
+    pub group: Ref<FormXObject>,
+
+    /// backdrop color in the group's color space, for `Luminosity` masks
+    #[pdf(key="BC")]
+    pub backdrop: Option<Vec<f32>>,
+
+    /// transfer function applied to the mask values, or `/Identity`
+    #[pdf(key="TR")]
+    pub transfer: Option<Primitive>,
+}
+
+impl GraphicsStateParameters {
+    /// The non-stroking overprint flag, falling back to `OP`.
+    pub fn fill_overprint(&self) -> Option<bool> {
+        self.overprint_fill.or(self.overprint)
+    }
+    pub fn nonzero_overprint_mode(&self) -> Option<bool> {
+        self.overprint_mode.map(|m| m == 1)
+    }
+}
 
 #[derive(Object, Debug, DataSize)]
 #[pdf(is_stream)]
@@ -541,6 +961,41 @@ impl ImageXObject {
         Ok(ImageXObject { inner })
     }
 
+    /// The soft mask image, if any.
+    pub fn smask(&self, resolve: &impl Resolve) -> Option<Result<ImageXObject>> {
+        let r = self.inner.info.smask?;
+        Some(resolve.get(r).map(|s| ImageXObject { inner: (*s).clone() }))
+    }
+
+    /// Decode a stencil mask (`ImageMask true`) into one byte per pixel, row by row:
+    /// 255 where the current fill color is painted, 0 where the page shows through.
+    pub fn stencil_mask(&self, resolve: &impl Resolve) -> Result<Vec<u8>> {
+        if !self.image_mask {
+            bail!("image is not a stencil mask");
+        }
+        let (width, height) = (self.width as usize, self.height as usize);
+
+        // with the default `/Decode [0 1]` a sample of 0 marks painted pixels.
+        let paint_bit = match self.decode.as_deref() {
+            Some(&[d0, d1]) if d0 > d1 => 1,
+            _ => 0,
+        };
+        let data = self.image_data(resolve)?;
+
+        let mut mask = Vec::with_capacity(width * height);
+        if let Some(StreamFilter::CCITTFaxDecode(_)) = self.inner.info.filters.last() {
+            // the fax decoder already produces a byte per pixel (0 for black, 255 for white)
+            if data.len() < width * height {
+                bail!("not enough image data: {} < {}", data.len(), width * height);
+            }
+            mask.extend(data[.. width * height].iter().map(|&b| if (b >> 7) == paint_bit { 255 } else { 0 }));
+        } else {
+            let samples = unpack_samples(&data, 1, width, height, false)?;
+            mask.extend(samples.iter().map(|&b| if b == paint_bit { 255 } else { 0 }));
+        }
+        Ok(mask)
+    }
+
     /// Decode everything except for the final image encoding (jpeg, jbig2, jp2k, ...)
     pub fn raw_image_data(&self, resolve: &impl Resolve) -> Result<(Arc<[u8]>, Option<&StreamFilter>)> {
         match self.inner.inner_data {
@@ -601,6 +1056,59 @@ impl ImageXObject {
     }
 }
 
+/// Unpack image samples of 1, 2, 4, 8 or 16 bits into one byte per sample, dropping the padding at the end of each row.
+/// 
+/// With `scale`, sample values below 8 bits are scaled to `0 ..= 255`. Pass `false` for `Indexed` images,
+/// so the values stay valid palette indices. 16 bit samples are reduced to their high byte.
+pub fn unpack_samples(data: &[u8], bits_per_component: u8, samples_per_row: usize, rows: usize, scale: bool) -> Result<Vec<u8>> {
+    let stride = match bits_per_component {
+        1 | 2 | 4 | 8 | 16 => (samples_per_row * bits_per_component as usize).div_ceil(8),
+        n => bail!("invalid bits per component {}", n)
+    };
+    if data.len() < stride * rows {
+        bail!("not enough image data: {} < {}", data.len(), stride * rows);
+    }
+    let mut out = Vec::with_capacity(samples_per_row * rows);
+    for row in data.chunks_exact(stride).take(rows) {
+        match bits_per_component {
+            8 => out.extend_from_slice(row),
+            16 => out.extend(row.iter().step_by(2)),
+            bpc => {
+                let per_byte = 8 / bpc as usize;
+                let mask = (1u8 << bpc) - 1;
+                let factor = if scale { 255 / mask } else { 1 };
+                out.extend((0 .. samples_per_row).map(|i| {
+                    let shift = 8 - bpc as usize * (i % per_byte + 1);
+                    ((row[i / per_byte] >> shift) & mask) * factor
+                }));
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Remap 8 bit samples in place according to a `/Decode` array.
+/// 
+/// `decode` holds a `[min max]` pair for each of the interleaved components.
+/// This only applies to color spaces whose components range over `0 ..= 1` (gray, RGB, CMYK);
+/// `[1 0]` on a gray image inverts it.
+pub fn apply_decode(samples: &mut [u8], decode: &[f32]) {
+    let n = decode.len() / 2;
+    if n == 0 {
+        return;
+    }
+    let ranges: Vec<(f32, f32)> = decode.chunks_exact(2).map(|r| (r[0], r[1])).collect();
+    if ranges.iter().all(|&r| r == (0., 1.)) {
+        return;
+    }
+    for pixel in samples.chunks_mut(n) {
+        for (s, &(min, max)) in pixel.iter_mut().zip(&ranges) {
+            let v = min + (*s as f32 / 255.) * (max - min);
+            *s = (v * 255.).round().clamp(0., 255.) as u8;
+        }
+    }
+}
+
 #[derive(Object, Debug, DataSize)]
 #[pdf(Type="XObject", Subtype="PS")]
 pub struct PostScriptDict {
@@ -656,9 +1164,14 @@ pub struct ImageDict {
     #[pdf(key="ID")]
     pub id: Option<PdfString>,
 
+    /// Soft mask image giving the alpha of each sample. See also [`ImageXObject::smask`].
     #[pdf(key="SMask")]
     pub smask: Option<Ref<Stream<ImageDict>>>,
 
+    /// Only for soft mask images: the color (in the parent image's color space) the parent image was preblended with.
+    #[pdf(key="Matte")]
+    pub matte: Option<Vec<f32>>,
+
     // OPI: dict
     // Metadata: stream
     // OC: dict
@@ -711,14 +1224,15 @@ pub struct FormDict {
     #[pdf(key="BBox")]
     pub bbox: Rect,
 
-    #[pdf(key="Matrix")]
-    pub matrix: Option<Primitive>,
+    /// maps form space into user space. Identity if absent.
+    #[pdf(key="Matrix", default="Matrix::default()")]
+    pub matrix: Matrix,
 
     #[pdf(key="Resources")]
     pub resources: Option<MaybeRef<Resources>>,
 
     #[pdf(key="Group")]
-    pub group: Option<Dictionary>,
+    pub group: Option<TransparencyGroup>,
 
     #[pdf(key="Ref")]
     pub reference: Option<Dictionary>,
@@ -742,6 +1256,23 @@ pub struct FormDict {
     pub other: Dictionary,
 }
 
+/// Group attributes of a form XObject or page (`/Group << /S /Transparency ... >>`).
+#[derive(Object, ObjectWrite, Debug, DataSize)]
+#[pdf(Type="Group?", S="Transparency?")]
+pub struct TransparencyGroup {
+    /// blending color space of the group
+    #[pdf(key="CS")]
+    pub color_space: Option<ColorSpace>,
+
+    /// isolated: composited onto a transparent backdrop instead of the page content
+    #[pdf(key="I", default="false")]
+    pub isolated: bool,
+
+    /// knockout: each element is composited with the initial backdrop rather than the previous elements
+    #[pdf(key="K", default="false")]
+    pub knockout: bool,
+}
+
 #[derive(Object, ObjectWrite, Debug, Clone, DataSize)]
 pub struct InteractiveFormDictionary {
     #[pdf(key="Fields")]
@@ -1289,6 +1820,153 @@ impl Object for Rect {
         })
     }
 }
+#[derive(Object, ObjectWrite, Debug, DataSize)]
+#[pdf(Type="Annot?")]
+pub struct Annot {
+    #[pdf(key="Subtype")]
+    pub subtype: Name,
+
+    /// location on the page, in default user space
+    #[pdf(key="Rect")]
+    pub rect: Option<Rect>,
+
+    #[pdf(key="Contents")]
+    pub contents: Option<PdfString>,
+
+    /// annotation flags, see `Annot::HIDDEN` etc.
+    #[pdf(key="F", default="0")]
+    pub annot_flags: u32,
+
+    #[pdf(key="AP")]
+    pub appearance_streams: Option<MaybeRef<AppearanceStreams>>,
+
+    /// selects the appearance if the entries of `/AP` are state dictionaries
+    #[pdf(key="AS")]
+    pub appearance_state: Option<Name>,
+
+    #[pdf(other)]
+    pub other: Dictionary,
+}
+impl Annot {
+    pub const INVISIBLE: u32 = 1 << 0;
+    pub const HIDDEN: u32 = 1 << 1;
+    pub const PRINT: u32 = 1 << 2;
+    pub const NO_VIEW: u32 = 1 << 5;
+
+    /// Whether the annotation should be drawn when viewing the page.
+    pub fn is_visible(&self) -> bool {
+        self.annot_flags & (Annot::HIDDEN | Annot::NO_VIEW) == 0
+    }
+    /// The normal appearance (`/AP /N`), picking the `/AS` state if there are several.
+    pub fn normal_appearance(&self) -> Option<Ref<FormXObject>> {
+        match self.appearance_streams.as_ref()?.normal {
+            AppearanceStreamEntry::Single(r) => Some(r),
+            AppearanceStreamEntry::Dict(ref states) => states.get(self.appearance_state.as_ref()?).copied(),
+        }
+    }
+    /// Maps the form space of an appearance stream to default user space, so that its bbox fills `/Rect`.
+    pub fn appearance_transform(&self, form: &FormDict) -> Option<Matrix> {
+        let rect = self.rect?.normalized();
+        let m = form.matrix;
+        let bbox = form.bbox;
+        // bounding box of the transformed form bbox
+        let corners = [(bbox.left, bbox.bottom), (bbox.left, bbox.top), (bbox.right, bbox.bottom), (bbox.right, bbox.top)]
+            .map(|(x, y)| (m.a * x + m.c * y + m.e, m.b * x + m.d * y + m.f));
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for (x, y) in corners {
+            x0 = x0.min(x);
+            y0 = y0.min(y);
+            x1 = x1.max(x);
+            y1 = y1.max(y);
+        }
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+        let sx = (rect.right - rect.left) / (x1 - x0);
+        let sy = (rect.top - rect.bottom) / (y1 - y0);
+        let (tx, ty) = (rect.left - x0 * sx, rect.bottom - y0 * sy);
+        Some(Matrix {
+            a: m.a * sx,
+            b: m.b * sy,
+            c: m.c * sx,
+            d: m.d * sy,
+            e: m.e * sx + tx,
+            f: m.f * sy + ty,
+        })
+    }
+}
+
+#[derive(Object, ObjectWrite, Debug, DataSize)]
+pub struct AppearanceStreams {
+    #[pdf(key="N")]
+    pub normal: AppearanceStreamEntry,
+
+    #[pdf(key="R")]
+    pub rollover: Option<AppearanceStreamEntry>,
+
+    #[pdf(key="D")]
+    pub down: Option<AppearanceStreamEntry>,
+}
+
+#[derive(Debug, DataSize)]
+pub enum AppearanceStreamEntry {
+    Single(Ref<FormXObject>),
+    /// one appearance per state, selected by `/AS`
+    Dict(HashMap<Name, Ref<FormXObject>>),
+}
+impl Object for AppearanceStreamEntry {
+    fn from_primitive(p: Primitive, resolve: &impl Resolve) -> Result<Self> {
+        match p {
+            Primitive::Reference(r) => match resolve.resolve(r)? {
+                Primitive::Dictionary(dict) => Ok(AppearanceStreamEntry::Dict(t!(Object::from_primitive(Primitive::Dictionary(dict), resolve)))),
+                _ => Ok(AppearanceStreamEntry::Single(Ref::new(r))),
+            },
+            p @ Primitive::Dictionary(_) => Ok(AppearanceStreamEntry::Dict(t!(Object::from_primitive(p, resolve)))),
+            p => Err(PdfError::UnexpectedPrimitive { expected: "Reference or Dictionary", found: p.get_debug_name() })
+        }
+    }
+}
+impl ObjectWrite for AppearanceStreamEntry {
+    fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
+        match *self {
+            AppearanceStreamEntry::Single(ref r) => r.to_primitive(update),
+            AppearanceStreamEntry::Dict(ref d) => d.to_primitive(update),
+        }
+    }
+}
+
+/// Map an angle in degrees onto 0, 90, 180 or 270, rounding to the nearest quarter turn.
+pub fn normalize_rotation(degrees: i32) -> i32 {
+    let quarters = (degrees as f32 / 90.).round() as i32;
+    quarters.rem_euclid(4) * 90
+}
+
+impl Rect {
+    /// Same rectangle with `left <= right` and `bottom <= top`.
+    pub fn normalized(self) -> Rect {
+        Rect {
+            left:   self.left.min(self.right),
+            bottom: self.bottom.min(self.top),
+            right:  self.left.max(self.right),
+            top:    self.bottom.max(self.top),
+        }
+    }
+    /// Intersection of both rectangles, or `None` if they don't overlap.
+    pub fn intersect(self, other: Rect) -> Option<Rect> {
+        let (a, b) = (self.normalized(), other.normalized());
+        let r = Rect {
+            left:   a.left.max(b.left),
+            bottom: a.bottom.max(b.bottom),
+            right:  a.right.min(b.right),
+            top:    a.top.min(b.top),
+        };
+        if r.left < r.right && r.bottom < r.top {
+            Some(r)
+        } else {
+            None
+        }
+    }
+}
 impl ObjectWrite for Rect {
     fn to_primitive(&self, update: &mut impl Updater) -> Result<Primitive> {
         Primitive::array::<f32, _, _, _>([self.left, self.top, self.right, self.bottom].iter(), update)
@@ -1409,6 +2087,248 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_annot() {
+        let data = b"<< /Type /Annot /Subtype /Widget /Rect [100 100 200 150] /F 4 \
+            /AP << /N << /On 5 0 R /Off 6 0 R >> >> /AS /Off >>";
+        let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+        let annot = Annot::from_primitive(p, &NoResolve).unwrap();
+        assert!(annot.is_visible());
+        assert_eq!(annot.normal_appearance().unwrap().get_inner().id, 6);
+
+        let form = FormDict {
+            form_type: 1,
+            name: None,
+            last_modified: None,
+            bbox: Rect { left: 0., bottom: 0., right: 50., top: 25. },
+            matrix: Matrix::default(),
+            resources: None,
+            group: None,
+            reference: None,
+            metadata: None,
+            piece_info: None,
+            struct_parent: None,
+            struct_parents: None,
+            opi: None,
+            other: Dictionary::new(),
+        };
+        let m = annot.appearance_transform(&form).unwrap();
+        assert_eq!((m.a, m.d, m.e, m.f), (2., 2., 100., 100.));
+    }
+
+    #[test]
+    fn test_overprint() {
+        let p = crate::parser::parse(b"<< /OP true /OPM 1 >>", &NoResolve, ParseFlags::ANY).unwrap();
+        let gs = GraphicsStateParameters::from_primitive(p, &NoResolve).unwrap();
+        assert_eq!(gs.fill_overprint(), Some(true));
+        assert_eq!(gs.nonzero_overprint_mode(), Some(true));
+
+        let p = crate::parser::parse(b"<< /OP true /op false >>", &NoResolve, ParseFlags::ANY).unwrap();
+        let gs = GraphicsStateParameters::from_primitive(p, &NoResolve).unwrap();
+        assert_eq!(gs.fill_overprint(), Some(false));
+        assert_eq!(gs.nonzero_overprint_mode(), None);
+    }
+
+    #[test]
+    fn test_blend_mode() {
+        let parse = |data: &[u8]| {
+            let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+            GraphicsStateParameters::from_primitive(p, &NoResolve).unwrap().blend_mode
+        };
+        assert_eq!(parse(b"<< /BM /Multiply >>"), Some(BlendMode::Multiply));
+        assert_eq!(parse(b"<< /BM [/Foo /Screen /Normal] >>"), Some(BlendMode::Screen));
+        assert_eq!(parse(b"<< /BM /Compatible >>"), Some(BlendMode::Normal));
+        assert_eq!(parse(b"<< /BM 5 >>"), Some(BlendMode::Normal));
+        assert_eq!(parse(b"<< >>"), None);
+        assert!(!BlendMode::Luminosity.is_separable());
+    }
+
+    #[test]
+    fn test_transparency_group() {
+        let data = b"<< /Subtype /Form /BBox [0 0 10 10] /Group << /S /Transparency /CS /DeviceRGB /I true >> >>";
+        let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+        let form = FormDict::from_primitive(p, &NoResolve).unwrap();
+        let group = form.group.unwrap();
+        assert!(matches!(group.color_space, Some(ColorSpace::DeviceRGB)));
+        assert!(group.isolated && !group.knockout);
+
+        // /S is optional, but written
+        let data = b"<< /Subtype /Form /BBox [0 0 10 10] /Group << /K true >> >>";
+        let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+        let group = FormDict::from_primitive(p, &NoResolve).unwrap().group.unwrap();
+        assert!(group.knockout);
+        let dict = group.to_dict(&mut NoUpdate).unwrap();
+        assert_eq!(dict.get("S").and_then(|p| p.as_name().ok()), Some("Transparency"));
+    }
+
+    #[test]
+    fn test_soft_mask() {
+        let parse = |data: &[u8]| {
+            let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+            GraphicsStateParameters::from_primitive(p, &NoResolve).unwrap().smask
+        };
+        assert!(matches!(parse(b"<< /SMask /None >>"), Some(SoftMask::None)));
+        match parse(b"<< /SMask << /Type /Mask /S /Luminosity /G 12 0 R /BC [1] >> >>") {
+            Some(SoftMask::Mask(mask)) => {
+                assert_eq!(mask.subtype, SoftMaskType::Luminosity);
+                assert_eq!(mask.group.get_inner().id, 12);
+                assert_eq!(mask.backdrop, Some(vec![1.]));
+            }
+            m => panic!("unexpected {:?}", m)
+        }
+        // missing /G
+        assert!(matches!(parse(b"<< /SMask << /S /Luminosity >> >>"), Some(SoftMask::None)));
+    }
+
+    #[test]
+    fn test_color_space_lookup() {
+        let mut resources = Resources {
+            graphics_states: HashMap::new(),
+            color_spaces: HashMap::new(),
+            pattern: HashMap::new(),
+            shadings: HashMap::new(),
+            xobjects: HashMap::new(),
+            fonts: HashMap::new(),
+            properties: HashMap::new(),
+        };
+        resources.color_spaces.insert("CS0".into(), ColorSpace::DeviceCMYK);
+        assert!(matches!(resources.color_space("CS0"), Ok(ColorSpace::DeviceCMYK)));
+        assert!(matches!(resources.color_space("DeviceRGB"), Ok(ColorSpace::DeviceRGB)));
+        assert!(matches!(resources.color_space("Pattern"), Ok(ColorSpace::Pattern)));
+        assert!(matches!(resources.color_space("CS1"), Err(PdfError::MissingResource { .. })));
+    }
+
+    #[test]
+    fn test_normalize_rotation() {
+        assert_eq!(normalize_rotation(0), 0);
+        assert_eq!(normalize_rotation(450), 90);
+        assert_eq!(normalize_rotation(-90), 270);
+        assert_eq!(normalize_rotation(-540), 180);
+        assert_eq!(normalize_rotation(100), 90);
+    }
+
+    #[test]
+    fn test_rect_intersect() {
+        let media = Rect { left: 0., bottom: 0., right: 612., top: 792. };
+        let crop = Rect { left: 700., bottom: 50., right: 36., top: 500. };
+        let r = crop.intersect(media).unwrap();
+        assert_eq!((r.left, r.bottom, r.right, r.top), (36., 50., 612., 500.));
+
+        let outside = Rect { left: 1000., bottom: 0., right: 1100., top: 100. };
+        assert!(outside.intersect(media).is_none());
+    }
+
+    #[test]
+    fn test_shading_pattern() {
+        let data = b"<< /PatternType 2 /Shading << /ShadingType 2 /ColorSpace /DeviceRGB /Coords [0 0 100 0] \
+            /Function << /FunctionType 2 /Domain [0 1] /C0 [1 0 0] /C1 [0 0 1] /N 1 >> /Extend [true true] >> >>";
+        let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+        let pattern = Pattern::from_primitive(p, &NoResolve).unwrap();
+        assert!(pattern.dict().is_none());
+        let shading = match pattern {
+            Pattern::Shading(ref d) => d.shading.dict(),
+            _ => panic!("expected a shading pattern")
+        };
+        assert_eq!(shading.shading_type, ShadingType::Axial);
+        assert!(matches!(shading.color_space, ColorSpace::DeviceRGB));
+        assert_eq!(shading.coords.as_deref(), Some(&[0., 0., 100., 0.][..]));
+        assert_eq!(shading.extend.as_deref(), Some(&[true, true][..]));
+
+        let mut rgb = [0.; 3];
+        shading.function.as_ref().unwrap()[0].apply(&[0.5], &mut rgb).unwrap();
+        assert_eq!(rgb, [0.5, 0., 0.5]);
+
+        // write it and read it back
+        let p = pattern.to_primitive(&mut NoUpdate).unwrap();
+        assert_eq!(p.clone().into_dictionary().unwrap().get("PatternType"), Some(&Primitive::Integer(2)));
+        let shading = match Pattern::from_primitive(p, &NoResolve).unwrap() {
+            Pattern::Shading(d) => d.shading,
+            _ => panic!("expected a shading pattern")
+        };
+        let shading = shading.dict();
+        assert_eq!(shading.shading_type, ShadingType::Axial);
+        assert_eq!(shading.coords.as_deref(), Some(&[0., 0., 100., 0.][..]));
+        assert_eq!(shading.extend.as_deref(), Some(&[true, true][..]));
+        shading.function.as_ref().unwrap()[0].apply(&[0.25], &mut rgb).unwrap();
+        assert_eq!(rgb, [0.75, 0., 0.25]);
+    }
+
+    #[test]
+    fn test_tiling_pattern() {
+        let data = b"<< /PatternType 1 /PaintType 2 /TilingType 1 /BBox [0 0 10 10] /XStep 20 /YStep 20 /Resources << >> >>";
+        let p = crate::parser::parse(data, &NoResolve, ParseFlags::ANY).unwrap();
+        let pattern = Pattern::from_primitive(p, &NoResolve).unwrap();
+        let dict = pattern.dict().unwrap();
+        assert_eq!(dict.paint_type, Some(PaintType::Uncolored));
+        assert_eq!(dict.tiling_type, Some(TilingType::ConstantSpacing));
+        assert_eq!((dict.x_step, dict.y_step), (20., 20.));
+        assert!(dict.resources.as_ref().is_none());
+    }
+
+    fn test_image(width: u32, height: u32, bits_per_component: i32, color_space: Option<ColorSpace>, decode: Option<Vec<f32>>, data: &[u8]) -> ImageXObject {
+        let image_mask = color_space.is_none();
+        let dict = ImageDict {
+            width,
+            height,
+            color_space,
+            bits_per_component: Some(bits_per_component),
+            intent: None,
+            image_mask,
+            mask: None,
+            decode,
+            interpolate: false,
+            struct_parent: None,
+            id: None,
+            smask: None,
+            matte: None,
+            other: Dictionary::new(),
+        };
+        ImageXObject { inner: Stream::new(dict, data) }
+    }
+
+    #[test]
+    fn test_stencil_mask() {
+        let data = [0b1010_0000, 0b0100_0000];
+        let image = test_image(3, 2, 1, None, None, &data);
+        assert_eq!(image.stencil_mask(&NoResolve).unwrap(), [0, 255, 0, 255, 0, 255]);
+
+        let image = test_image(3, 2, 1, None, Some(vec![1., 0.]), &data);
+        assert_eq!(image.stencil_mask(&NoResolve).unwrap(), [255, 0, 255, 0, 255, 0]);
+    }
+
+    #[test]
+    fn test_unpack_samples() {
+        // 3 pixels of 4 bit indices per row, padded to 2 bytes
+        let data = [0x1f, 0x70, 0x02, 0xa0];
+        assert_eq!(unpack_samples(&data, 4, 3, 2, false).unwrap(), [1, 15, 7, 0, 2, 10]);
+        assert_eq!(unpack_samples(&data, 4, 3, 2, true).unwrap(), [17, 255, 119, 0, 34, 170]);
+
+        // 10 pixels of 1 bit gray per row
+        let data = [0b1100_0011, 0b0100_0000, 0b0000_0000, 0b1000_0000];
+        assert_eq!(unpack_samples(&data, 1, 10, 2, true).unwrap(),
+            [255, 255, 0, 0, 0, 0, 255, 255, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 255, 0]);
+
+        assert_eq!(unpack_samples(&[0b11_10_01_00], 2, 4, 1, false).unwrap(), [3, 2, 1, 0]);
+        assert_eq!(unpack_samples(&[0b11_10_01_00], 2, 4, 1, true).unwrap(), [255, 170, 85, 0]);
+        assert_eq!(unpack_samples(&[0x12, 0x34, 0xab, 0xcd], 16, 2, 1, true).unwrap(), [0x12, 0xab]);
+        assert!(unpack_samples(&[0], 1, 9, 1, true).is_err());
+    }
+
+    #[test]
+    fn test_apply_decode() {
+        let mut gray = [0, 64, 255];
+        apply_decode(&mut gray, &[1., 0.]);
+        assert_eq!(gray, [255, 191, 0]);
+
+        let mut rgb = [0, 128, 255, 255, 0, 128];
+        apply_decode(&mut rgb, &[0., 1., 1., 0., 0., 0.5]);
+        assert_eq!(rgb, [0, 127, 128, 255, 255, 64]);
+
+        let mut unchanged = [1, 2, 3];
+        apply_decode(&mut unchanged, &[0., 1.]);
+        assert_eq!(unchanged, [1, 2, 3]);
+    }
+
     #[test]
     fn test_field_type() {
         assert_eq!(
@@ -1,3 +1,5 @@
+use pdf::object::Resolve;
+
 #[test]
 fn infinite_loop_invalid_file() {
     assert!(pdf::file::File::from_data(b"startxref%PDF-".as_ref()).is_err());
@@ -8,3 +10,292 @@ fn ending_angle_bracket() {
     assert!(pdf::file::File::from_data(b"%PDF-startxref>".as_ref()).is_err());
     assert!(pdf::file::File::from_data(b"%PDF-startxref<".as_ref()).is_err());
 }
+
+/// Assemble a file from the bodies of objects 1, 2, ... with object 1 as the catalog.
+fn build_pdf(objects: &[&str]) -> Vec<u8> {
+    let mut out = b"%PDF-1.7\n".to_vec();
+    let mut offsets = vec![];
+    for (i, obj) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, obj).as_bytes());
+    }
+    let xref = out.len();
+    out.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        out.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    out.extend_from_slice(format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref).as_bytes());
+    out
+}
+
+#[test]
+fn self_referencing_type3_font() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Resources 5 0 R >>",
+        "<< /Type /Font /Subtype /Type3 /FontMatrix [1 0 0 1 0 0] /CharProcs << >> \
+            /FirstChar 0 /LastChar 0 /Widths [0] /Resources 5 0 R >>",
+        "<< /Font << /F1 4 0 R >> >>",
+    ]);
+    // the font's resources contain the font again, and there is no /FontBBox
+    let file = pdf::file::File::from_data(data).unwrap();
+    let page = file.get_page(0).unwrap();
+    let font = page.resources().unwrap().font("F1").unwrap();
+    let type3 = match font.data {
+        pdf::font::FontData::Type3(ref t) => t,
+        _ => panic!("not a Type3 font"),
+    };
+    assert_eq!(type3.font_bbox.right, 0.);
+    let resources = type3.resources(&file).unwrap().unwrap();
+    assert!(resources.fonts.contains_key("F1"));
+}
+
+#[test]
+fn self_referencing_form() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Resources << /XObject << /X1 4 0 R >> >> >>",
+        "<< /Type /XObject /Subtype /Form /BBox [0 0 1 1] /Resources << /XObject << /X1 4 0 R >> >> /Length 6 >>\n\
+            stream\n/X1 Do\nendstream",
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    let page = file.get_page(0).unwrap();
+    let r = page.resources().unwrap().xobject("X1").unwrap();
+    let xobject = file.get(r).unwrap();
+    let form = match *xobject {
+        pdf::object::XObject::Form(ref form) => form,
+        _ => panic!("not a form"),
+    };
+    let inner = form.dict().resources.as_ref().unwrap().xobject("X1").unwrap();
+    assert_eq!(inner, r);
+    assert!(file.get(inner).is_ok());
+}
+
+#[test]
+fn recursive_reference_error() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] >>",
+        "<< /Type /Font /Subtype /Type0 /BaseFont /A /Encoding /Identity-H /DescendantFonts [4 0 R] >>",
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    let err = file.get(pdf::object::Ref::<pdf::font::Font>::from_id(4)).unwrap_err();
+    assert!(matches!(err.root_cause(), pdf::error::PdfError::RecursiveReference { obj_nr: 4 }));
+    // the failed lookup does not affect unrelated objects
+    assert!(file.get_page(0).is_ok());
+}
+
+#[test]
+fn malformed_annotations() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Annots [4 0 R << /Rect [0 0 1 1] >> \
+            << /Subtype /Widget /AP << /N null >> >>] >>",
+        "<< /Type /Annot /Subtype /Text /Rect [0 0 5 5] >>",
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    let page = file.get_page(0).unwrap();
+    let annots = page.annotations(&file).unwrap();
+    assert_eq!(annots.len(), 1);
+    assert_eq!(annots[0].subtype.as_str(), "Text");
+}
+
+#[test]
+fn malformed_graphics_state() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] \
+            /Resources << /ExtGState << /GS1 << /SMask << /S /Luminosity >> /BM 5 >> >> >> >>",
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    let page = file.get_page(0).unwrap();
+    let gs = &page.resources().unwrap().graphics_states["GS1"];
+    assert!(matches!(gs.smask, Some(pdf::object::SoftMask::None)));
+    assert_eq!(gs.blend_mode, Some(pdf::object::BlendMode::Normal));
+}
+
+#[test]
+fn inherited_rotation() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R 4 0 R] /Count 2 /Rotate 90 /MediaBox [0 0 10 10] >>",
+        "<< /Type /Page /Parent 2 0 R >>",
+        "<< /Type /Page /Parent 2 0 R /Rotate 0 >>",
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    assert_eq!(file.get_page(0).unwrap().rotation().unwrap(), 90);
+    assert_eq!(file.get_page(1).unwrap().rotation().unwrap(), 0);
+}
+
+/// `UniGB-UCS2-H` and `-V`, cut down but laid out like the files of Adobe's cmap-resources.
+const UNIGB_UCS2_H: &str = "%!PS-Adobe-3.0 Resource-CMap
+%%DocumentNeededResources: ProcSet (CIDInit)
+%%IncludeResource: ProcSet (CIDInit)
+%%BeginResource: CMap (UniGB-UCS2-H)
+%%Title: (UniGB-UCS2-H Adobe GB1 5)
+%%Version: 13.005
+/CIDInit /ProcSet findresource begin
+
+12 dict begin
+
+begincmap
+
+/CIDSystemInfo 3 dict dup begin
+  /Registry (Adobe) def
+  /Ordering (GB1) def
+  /Supplement 5 def
+end def
+
+/CMapName /UniGB-UCS2-H def
+/CMapVersion 13.005 def
+/CMapType 1 def
+
+/XUID [1 10 25427] def
+
+/WMode 0 def
+
+1 begincodespacerange
+  <0000>   <ffff>
+endcodespacerange
+
+2 begincidrange
+<0020> <007e>     7716
+<4e00> <4e01>     4162
+endcidrange
+
+1 begincidchar
+<2013> 7174
+endcidchar
+endcmap
+CMapName currentdict /CMap defineresource pop
+end
+end
+
+%%EndResource
+%%EOF
+";
+const UNIGB_UCS2_V: &str = "%!PS-Adobe-3.0 Resource-CMap
+%%DocumentNeededResources: ProcSet (CIDInit)
+%%DocumentNeededResources: CMap (UniGB-UCS2-H)
+%%IncludeResource: ProcSet (CIDInit)
+%%IncludeResource: CMap (UniGB-UCS2-H)
+%%BeginResource: CMap (UniGB-UCS2-V)
+/CIDInit /ProcSet findresource begin
+
+12 dict begin
+
+begincmap
+
+/UniGB-UCS2-H usecmap
+
+/CIDSystemInfo 3 dict dup begin
+  /Registry (Adobe) def
+  /Ordering (GB1) def
+  /Supplement 5 def
+end def
+
+/CMapName /UniGB-UCS2-V def
+/CMapVersion 13.005 def
+/CMapType 1 def
+
+/XUID [1 10 25428] def
+
+/WMode 1 def
+
+1 begincidchar
+<2013> 7706
+endcidchar
+endcmap
+CMapName currentdict /CMap defineresource pop
+end
+end
+
+%%EndResource
+%%EOF
+";
+
+#[test]
+fn predefined_cmap_from_options() {
+    let dir = std::env::temp_dir().join(format!("pdf-xref-cmaps-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("UniGB-UCS2-H"), UNIGB_UCS2_H).unwrap();
+    std::fs::write(dir.join("UniGB-UCS2-V"), UNIGB_UCS2_V).unwrap();
+
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Resources << /Font << /F1 4 0 R >> >> >>",
+        "<< /Type /Font /Subtype /Type0 /BaseFont /STSong-Light-UniGB-UCS2-V /Encoding /UniGB-UCS2-V /DescendantFonts [
+            << /Type /Font /Subtype /CIDFontType0 /BaseFont /STSong-Light
+                /CIDSystemInfo << /Registry (Adobe) /Ordering (GB1) /Supplement 5 >>
+                /FontDescriptor << /FontName /STSong-Light /Flags 4 /FontBBox [0 0 1 1] /ItalicAngle 0 >> >>
+        ] >>",
+    ]);
+    let cmap = |options| {
+        let file = pdf::file::File::from_data_with_options(data.clone(), options).unwrap();
+        let page = file.get_page(0).unwrap();
+        let font = page.resources().unwrap().font("F1").unwrap();
+        font.cmap().cloned()
+    };
+    // no CMaps are bundled
+    assert!(cmap(pdf::object::ParseOptions::strict()).is_none());
+
+    let mut options = pdf::object::ParseOptions::strict();
+    options.cmap_dir = Some(dir.clone());
+    let cmap = cmap(options).unwrap();
+    assert_eq!(cmap.name.as_deref(), Some("UniGB-UCS2-V"));
+    assert!(cmap.vertical);
+    // the vertical form, then the ranges pulled in by usecmap
+    assert_eq!(cmap.cid(0x2013), Some(7706));
+    assert_eq!(cmap.cid(0x0041), Some(7749));
+    assert_eq!(cmap.cid(0x4e01), Some(4163));
+    assert_eq!(cmap.codes(&[0x4e, 0x00, 0x00, 0x41]).collect::<Vec<_>>(), [0x4e00, 0x0041]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn resource_font_info() {
+    let font = |name: &str| format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} >>", name);
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Resources << /Font << /F2 4 0 R /F1 5 0 R >> \
+            /ExtGState << /GS2 << /Font [6 0 R 12] >> /GS3 << /Font [99 0 R 12] >> /GS1 << /Font [7 0 R 12] >> >> >> >>",
+        &font("Two"),
+        &font("One"),
+        &font("StateTwo"),
+        &font("StateOne"),
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    let page = file.get_page(0).unwrap();
+    let names: Vec<_> = page.resources().unwrap().font_info(&file).into_iter()
+        .map(|info| info.base_font.unwrap().as_str().to_owned())
+        .collect();
+    assert_eq!(names, ["One", "Two", "StateOne", "StateTwo"]);
+}
+
+#[test]
+fn shading_resources() {
+    let data = build_pdf(&[
+        "<< /Type /Catalog /Pages 2 0 R >>",
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Resources << /Shading << /Sh1 4 0 R /Sh2 << /ShadingType 2 >> >> >> >>",
+        "<< /ShadingType 2 /ColorSpace /DeviceGray /Coords [0 0 10 0] /Function << /FunctionType 3 /Domain [0 1] \
+            /Bounds [0.5] /Encode [0 1 0 1] /Functions [<< /FunctionType 2 /Domain [0 1] /C0 [0] /C1 [1] /N 1 >> \
+            << /FunctionType 2 /Domain [0 1] /C0 [1] /C1 [0] /N 1 >>] >> >>",
+    ]);
+    let file = pdf::file::File::from_data(data).unwrap();
+    let page = file.get_page(0).unwrap();
+    let resources = page.resources().unwrap();
+    let shading = resources.shading("Sh1", &file).unwrap();
+    let mut out = [0.];
+    shading.dict().function.as_ref().unwrap()[0].apply(&[0.25], &mut out).unwrap();
+    assert!((out[0] - 0.5).abs() < 1e-6);
+    // the broken shading only fails its own lookup
+    assert!(resources.shading("Sh2", &file).is_err());
+}
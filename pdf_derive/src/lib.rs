@@ -187,8 +187,8 @@ impl FieldAttrs {
 /// Just the attributes for the whole struct
 #[derive(Default)]
 struct GlobalAttrs {
-    /// List of checks to do in the dictionary (LHS is the key, RHS is the expected value)
-    checks: Vec<(String, String)>,
+    /// List of checks to do in the dictionary (LHS is the key, RHS is the expected value, and whether the key is required)
+    checks: Vec<(String, String, bool)>,
     type_name: Option<String>,
     type_required: bool,
     is_stream: bool
@@ -226,7 +226,16 @@ impl GlobalAttrs {
                             }
                         } else {
                             match lit {
-                                Lit::Str(ref value) => attrs.checks.push((path.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<String>>().join("::"), value.value())),
+                                Lit::Str(ref value) => {
+                                    let key = path.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<String>>().join("::");
+                                    let mut value = value.value();
+                                    // a trailing '?' makes the key optional, as for "Type"
+                                    let required = !value.ends_with('?');
+                                    if !required {
+                                        value.pop();
+                                    }
+                                    attrs.checks.push((key, value, required));
+                                }
                                 _ => panic!("Other checks must have RHS String."),
                             }
                         }
@@ -568,9 +577,9 @@ fn impl_object_for_struct(ast: &DeriveInput, fields: &Fields) -> SynStream {
         quote! { #name: #name, }
     });
 
-    let checks: Vec<_> = attrs.checks.iter().map(|&(ref key, ref val)|
+    let checks: Vec<_> = attrs.checks.iter().map(|&(ref key, ref val, required)|
         quote! {
-            dict.expect(#typ, #key, #val, true)?;
+            dict.expect(#typ, #key, #val, #required)?;
         }
     ).collect();
 
@@ -630,7 +639,7 @@ fn impl_objectwrite_for_struct(ast: &DeriveInput, fields: &Fields) -> SynStream
             }
         }
     );
-    let checks_code = attrs.checks.iter().map(|&(ref key, ref val)|
+    let checks_code = attrs.checks.iter().map(|(key, val, _)|
         quote! {
             dict.insert(#key, pdf::primitive::Primitive::Name(#val.into()));
         }